@@ -0,0 +1,52 @@
+#![feature(test)]
+
+extern crate ff;
+extern crate rand;
+extern crate test;
+
+use ff::Field;
+use rand::{Rand, SeedableRng, XorShiftRng};
+
+mod fr {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "52435875175126190479447740508185965837690552500527637822603658699938581184513"]
+    #[PrimeFieldGenerator = "7"]
+    pub struct Fr(FrRepr);
+}
+
+use fr::Fr;
+
+const SAMPLES: usize = 64;
+
+fn inputs() -> (Vec<Fr>, Vec<Fr>) {
+    let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let a = (0..SAMPLES).map(|_| Fr::rand(&mut rng)).collect();
+    let b = (0..SAMPLES).map(|_| Fr::rand(&mut rng)).collect();
+
+    (a, b)
+}
+
+#[bench]
+fn bench_sum_of_products(bencher: &mut test::Bencher) {
+    let (a, b) = inputs();
+
+    bencher.iter(|| Fr::sum_of_products(test::black_box(&a), test::black_box(&b)));
+}
+
+#[bench]
+fn bench_sum_of_products_naive(bencher: &mut test::Bencher) {
+    let (a, b) = inputs();
+
+    bencher.iter(|| {
+        let mut acc = Fr::zero();
+        for (a, b) in test::black_box(&a).iter().zip(test::black_box(&b)) {
+            let mut tmp = *a;
+            tmp.mul_assign(b);
+            acc.add_assign(&tmp);
+        }
+        acc
+    });
+}
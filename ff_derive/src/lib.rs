@@ -14,7 +14,18 @@ use num_traits::{Zero, One, ToPrimitive};
 use num_bigint::BigUint;
 use std::str::FromStr;
 
-#[proc_macro_derive(PrimeField, attributes(PrimeFieldModulus, PrimeFieldGenerator))]
+/// Derives `Field`, `PrimeField` and `SqrtField` for a struct wrapping a single
+/// `Repr` type, which is generated alongside it.
+///
+/// - `PrimeFieldModulus`: the prime modulus, as a decimal string.
+/// - `PrimeFieldGenerator`: a multiplicative generator, which must be a quadratic
+///   nonresidue, as a decimal string.
+/// - `PrimeFieldReprLimbSize`: `"64"` (the default) or `"32"`. This selects the
+///   word size of the generated arithmetic only. The `Repr` is always backed by
+///   `[u64; limbs]`, as `PrimeFieldRepr` requires `AsRef<[u64]>`; with `"32"`,
+///   each 64-bit limb is split into two 32-bit words inside multiplication and
+///   reduction, and the `Repr` is otherwise unchanged.
+#[proc_macro_derive(PrimeField, attributes(PrimeFieldModulus, PrimeFieldGenerator, PrimeFieldReprLimbSize))]
 pub fn prime_field(
     input: proc_macro::TokenStream
 ) -> proc_macro::TokenStream
@@ -25,27 +36,108 @@ pub fn prime_field(
     // Parse the string representation
     let ast = syn::parse_derive_input(&s).unwrap();
 
+    // Report invalid input as a compiler error rather than a panic in the derive.
+    match prime_field_params(&ast) {
+        Ok((repr_ident, field, modulus, generator, limb_size)) => {
+            prime_field_tokens(&ast.ident, &repr_ident, &field, modulus, generator, limb_size)
+        },
+        Err(msg) => {
+            quote! {
+                compile_error!(#msg);
+            }
+        }
+    }.parse().unwrap()
+}
+
+/// Fetch the parameters of the field from the attributes of the derived struct.
+fn prime_field_params(
+    ast: &syn::DeriveInput
+) -> Result<(syn::Ident, syn::Ident, BigUint, BigUint, LimbSize), String>
+{
     // The struct we're deriving for is a wrapper around a "Repr" type we must construct.
-    let repr_ident = fetch_wrapped_ident(&ast.body)
-                     .expect("PrimeField derive only operates over tuple structs of a single item");
+    let (repr_ident, field) = fetch_wrapped_ident(&ast.body)
+                              .ok_or("PrimeField derive only operates over structs of a single item")?;
 
     // We're given the modulus p of the prime field
-    let modulus: BigUint = fetch_attr("PrimeFieldModulus", &ast.attrs)
-                           .expect("Please supply a PrimeFieldModulus attribute")
-                           .parse().expect("PrimeFieldModulus should be a number");
+    let modulus: BigUint = fetch_attr("PrimeFieldModulus", &ast.attrs)?
+                           .ok_or("Please supply a PrimeFieldModulus attribute")?
+                           .parse().map_err(|_| "PrimeFieldModulus must be a decimal string")?;
 
     // We may be provided with a generator of p - 1 order. It is required that this generator be quadratic
     // nonresidue.
-    let generator: BigUint = fetch_attr("PrimeFieldGenerator", &ast.attrs)
-                             .expect("Please supply a PrimeFieldGenerator attribute")
-                             .parse().expect("PrimeFieldGenerator should be a number");
+    let generator: BigUint = fetch_attr("PrimeFieldGenerator", &ast.attrs)?
+                             .ok_or("Please supply a PrimeFieldGenerator attribute")?
+                             .parse().map_err(|_| "PrimeFieldGenerator must be a decimal string")?;
+
+    // We may be asked to perform arithmetic over 32-bit words, for targets without
+    // fast 64-bit multiplication. The Repr keeps its 64-bit limbs either way.
+    let limb_size = match fetch_attr("PrimeFieldReprLimbSize", &ast.attrs)? {
+        Some(ref s) if s == "32" => LimbSize::U32,
+        Some(ref s) if s == "64" => LimbSize::U64,
+        Some(_) => return Err("PrimeFieldReprLimbSize must be \"32\" or \"64\"".to_owned()),
+        None => LimbSize::U64
+    };
+
+    // By Euler's criterion, the generator is a nonresidue iff generator^((p - 1)/2) = -1.
+    let p_minus_1 = &modulus - BigUint::one();
+    if exp(generator.clone(), &(&p_minus_1 >> 1), &modulus) != p_minus_1 {
+        return Err("PrimeFieldGenerator must be a quadratic nonresidue".to_owned());
+    }
+
+    Ok((repr_ident, field, modulus, generator, limb_size))
+}
+
+#[test]
+fn test_generator_nonresidue() {
+    let params = |generator: &str| {
+        let s = format!(
+            "#[PrimeFieldModulus = \"52435875175126190479447740508185965837690552500527637822603658699938581184513\"] \
+             #[PrimeFieldGenerator = \"{}\"] \
+             struct Fr(FrRepr);",
+            generator
+        );
+        prime_field_params(&syn::parse_derive_input(&s).unwrap()).map(|_| ())
+    };
+
+    assert!(params("7").is_ok());
+    assert_eq!(params("4"), Err("PrimeFieldGenerator must be a quadratic nonresidue".to_owned()));
+    assert!(params("0").is_err());
+}
+
+#[test]
+fn test_named_field() {
+    let field = |s: &str| {
+        let s = format!(
+            "#[PrimeFieldModulus = \"52435875175126190479447740508185965837690552500527637822603658699938581184513\"] \
+             #[PrimeFieldGenerator = \"7\"] \
+             {}",
+            s
+        );
+        prime_field_params(&syn::parse_derive_input(&s).unwrap()).map(|(repr, field, _, _, _)| (repr, field))
+    };
 
+    assert_eq!(field("struct Fr(FrRepr);"), Ok((syn::Ident::from("FrRepr"), syn::Ident::from("0"))));
+    assert_eq!(field("struct Fr { inner: FrRepr }"), Ok((syn::Ident::from("FrRepr"), syn::Ident::from("inner"))));
+    assert!(field("struct Fr { inner: FrRepr, other: u64 }").is_err());
+}
+
+/// Generate the field implementation for the struct `name` wrapping `repr_ident`
+/// in its `field`, which is `0` for a tuple struct.
+fn prime_field_tokens(
+    name: &syn::Ident,
+    repr_ident: &syn::Ident,
+    field: &syn::Ident,
+    modulus: BigUint,
+    generator: BigUint,
+    limb_size: LimbSize
+) -> quote::Tokens
+{
     // The arithmetic in this library only works if the modulus*2 is smaller than the backing
     // representation. Compute the number of limbs we need.
     let mut limbs = 1;
     {
         let mod2 = (&modulus) << 1; // modulus * 2
-        let mut cur = BigUint::one() << 64; // always 64-bit limbs for now
+        let mut cur = BigUint::one() << 64; // the representation always has 64-bit limbs
         while cur < mod2 {
             limbs += 1;
             cur = cur << 64;
@@ -54,18 +146,100 @@ pub fn prime_field(
 
     let mut gen = quote::Tokens::new();
 
-    gen.append(prime_field_repr_impl(&repr_ident, limbs));
-    gen.append(prime_field_constants_and_sqrt(&ast.ident, &repr_ident, modulus, limbs, generator));
-    gen.append(prime_field_impl(&ast.ident, &repr_ident, limbs));
-    
-    // Return the generated impl
-    gen.parse().unwrap()
+    gen.append(prime_field_repr_impl(repr_ident, limbs));
+    // With the small-field feature, single-limb fields over 64-bit words are not kept in
+    // Montgomery form, and multiplication reduces its double-width product directly.
+    let small_field = cfg!(feature = "small-field") && limbs == 1 && limb_size.bits() == 64;
+
+    gen.append(prime_field_constants_and_sqrt(name, repr_ident, field, modulus.clone(), limbs, generator, limb_size, small_field));
+    gen.append(prime_field_impl(name, repr_ident, field, &modulus, limbs, limb_size, small_field));
+
+    gen.append(prime_field_ops_impl(name));
+
+    if cfg!(feature = "serde") {
+        gen.append(prime_field_serde_impl(name, limbs));
+    }
+
+    if cfg!(feature = "zeroize") {
+        gen.append(prime_field_zeroize_impl(name, repr_ident, field));
+    }
+
+    gen
+}
+
+/// The size of the machine words used by the field arithmetic. The representation
+/// always exposes 64-bit limbs, which are split into two words each when using
+/// 32-bit words.
+#[derive(Clone, Copy)]
+enum LimbSize {
+    U32,
+    U64
+}
+
+impl LimbSize {
+    fn bits(self) -> usize {
+        match self {
+            LimbSize::U32 => 32,
+            LimbSize::U64 => 64
+        }
+    }
+
+    /// The number of words needed to hold `limbs` 64-bit limbs.
+    fn words(self, limbs: usize) -> usize {
+        limbs * 64 / self.bits()
+    }
+
+    fn ty(self) -> quote::Tokens {
+        match self {
+            LimbSize::U32 => quote!{u32},
+            LimbSize::U64 => quote!{u64}
+        }
+    }
+
+    fn adc(self) -> quote::Tokens {
+        match self {
+            LimbSize::U32 => quote!{::ff::adc32},
+            LimbSize::U64 => quote!{::ff::adc}
+        }
+    }
+
+    fn mac(self) -> quote::Tokens {
+        match self {
+            LimbSize::U32 => quote!{::ff::mac_with_carry32},
+            LimbSize::U64 => quote!{::ff::mac_with_carry}
+        }
+    }
+
+    /// The constant holding -(m^{-1}) mod 2^bits.
+    fn inv(self) -> quote::Tokens {
+        match self {
+            LimbSize::U32 => quote!{INV32},
+            LimbSize::U64 => quote!{INV}
+        }
+    }
+
+    /// An expression indexable by the words of the modulus.
+    fn modulus(self) -> quote::Tokens {
+        match self {
+            LimbSize::U32 => quote!{MODULUS32},
+            LimbSize::U64 => quote!{MODULUS.0}
+        }
+    }
+
+    /// The internal method performing Montgomery reduction over these words.
+    fn mont_reduce(self) -> syn::Ident {
+        match self {
+            LimbSize::U32 => syn::Ident::from("mont_reduce32"),
+            LimbSize::U64 => syn::Ident::from("mont_reduce")
+        }
+    }
 }
 
-/// Fetches the ident being wrapped by the type we're deriving.
+/// Fetches the ident being wrapped by the type we're deriving, along with the
+/// field that holds it.
 fn fetch_wrapped_ident(
     body: &syn::Body
-) -> Option<syn::Ident>
+) -> Option<(syn::Ident, syn::Ident)>
 {
     match body {
         &syn::Body::Struct(ref variant_data) => {
@@ -74,7 +248,9 @@ fn fetch_wrapped_ident(
                 match fields[0].ty {
                     syn::Ty::Path(_, ref path) => {
                         if path.segments.len() == 1 {
-                            return Some(path.segments[0].ident.clone());
+                            // Tuple struct fields are unnamed, and accessed as `.0`.
+                            let field = fields[0].ident.clone().unwrap_or_else(|| syn::Ident::from("0"));
+                            return Some((path.segments[0].ident.clone(), field));
                         }
                     },
                     _ => {}
@@ -91,7 +267,7 @@ fn fetch_wrapped_ident(
 fn fetch_attr(
     name: &str,
     attrs: &[syn::Attribute]
-) -> Option<String>
+) -> Result<Option<String>, String>
 {
     for attr in attrs {
         if attr.name() == name {
@@ -99,21 +275,21 @@ fn fetch_attr(
                 syn::MetaItem::NameValue(_, ref val) => {
                     match val {
                         &syn::Lit::Str(ref s, _) => {
-                            return Some(s.clone())
+                            return Ok(Some(s.clone()))
                         },
                         _ => {
-                            panic!("attribute {} should be a string", name);
+                            return Err(format!("attribute {} should be a string", name));
                         }
                     }
                 },
                 _ => {
-                    panic!("attribute {} should be a string", name);
+                    return Err(format!("attribute {} should be a string", name));
                 }
             }
         }
     }
 
-    None
+    Ok(None)
 }
 
 // Implement PrimeFieldRepr for the wrapped ident `repr` with `limbs` limbs.
@@ -129,7 +305,7 @@ fn prime_field_repr_impl(
         impl ::rand::Rand for #repr {
             #[inline(always)]
             fn rand<R: ::rand::Rng>(rng: &mut R) -> Self {
-                #repr(rng.gen())
+                ::ff::PrimeFieldRepr::random(&mut ::ff::RngCompat(rng))
             }
         }
 
@@ -152,6 +328,20 @@ fn prime_field_repr_impl(
             }
         }
 
+        impl AsMut<[u64]> for #repr {
+            #[inline(always)]
+            fn as_mut(&mut self) -> &mut [u64] {
+                &mut self.0
+            }
+        }
+
+        impl From<[u64; #limbs]> for #repr {
+            #[inline(always)]
+            fn from(limbs: [u64; #limbs]) -> #repr {
+                #repr(limbs)
+            }
+        }
+
         impl From<u64> for #repr {
             #[inline(always)]
             fn from(val: u64) -> #repr {
@@ -336,9 +526,12 @@ fn test_exp() {
 fn prime_field_constants_and_sqrt(
     name: &syn::Ident,
     repr: &syn::Ident,
+    field: &syn::Ident,
     modulus: BigUint,
     limbs: usize,
-    generator: BigUint
+    generator: BigUint,
+    limb_size: LimbSize,
+    small_field: bool
 ) -> quote::Tokens
 {
     let modulus_num_bits = biguint_num_bits(modulus.clone());
@@ -349,8 +542,13 @@ fn prime_field_constants_and_sqrt(
     // reduce the cost of rejection sampling.
     let repr_shave_bits = (64 * limbs as u32) - biguint_num_bits(modulus.clone());
 
-    // Compute R = 2**(64 * limbs) mod m
-    let r = (BigUint::one() << (limbs * 64)) % &modulus;
+    // Compute R = 2**(64 * limbs) mod m. Small fields are not kept in Montgomery
+    // form, which amounts to R = 1.
+    let r = if small_field {
+        BigUint::one()
+    } else {
+        (BigUint::one() << (limbs * 64)) % &modulus
+    };
 
     // modulus - 1 = 2^s * t
     let mut s: usize = 0;
@@ -360,6 +558,9 @@ fn prime_field_constants_and_sqrt(
         s += 1;
     }
 
+    let t_minus_1_over_2 = biguint_to_u64_vec((&t - BigUint::one()) >> 1, limbs);
+    let modulus_minus_1_over_2 = biguint_to_u64_vec((&modulus - BigUint::one()) >> 1, limbs);
+
     // Compute 2^s root of unity given the generator
     let root_of_unity = biguint_to_u64_vec((exp(generator.clone(), &t, &modulus) * &r) % &modulus, limbs);
     let generator = biguint_to_u64_vec((generator.clone() * &r) % &modulus, limbs);
@@ -377,13 +578,13 @@ fn prime_field_constants_and_sqrt(
                     // Shank's algorithm for q mod 4 = 3
                     // https://eprint.iacr.org/2012/685.pdf (page 9, algorithm 2)
 
-                    let mut a1 = self.pow(#mod_minus_3_over_4);
+                    let mut a1 = self.pow_vartime(#mod_minus_3_over_4);
 
                     let mut a0 = a1;
                     a0.square();
                     a0.mul_assign(self);
 
-                    if a0.0 == #repr(#rneg) {
+                    if a0.#field == #repr(#rneg) {
                         None
                     } else {
                         a1.mul_assign(self);
@@ -392,27 +593,62 @@ fn prime_field_constants_and_sqrt(
                 }
             }
         }
-    } else if (&modulus % BigUint::from_str("16").unwrap()) == BigUint::from_str("1").unwrap() {
-        let mod_minus_1_over_2 = biguint_to_u64_vec((&modulus - BigUint::from_str("1").unwrap()) >> 1, limbs);
+    } else if (&modulus % BigUint::from_str("8").unwrap()) == BigUint::from_str("5").unwrap() {
+        let mod_plus_3_over_8 = biguint_to_u64_vec((&modulus + BigUint::from_str("3").unwrap()) >> 3, limbs);
+
+        // 2 is a quadratic nonresidue, so 2^((m-1)/4) is a square root of -1.
+        let sqrt_minus_one = exp(BigUint::from_str("2").unwrap(), &((&modulus - BigUint::one()) >> 2), &modulus);
+        let sqrt_minus_one = biguint_to_u64_vec((sqrt_minus_one * &r) % &modulus, limbs);
+
+        quote!{
+            impl ::ff::SqrtField for #name {
+                fn sqrt(&self) -> Option<Self> {
+                    // Square root for q mod 8 = 5. If a is a quadratic residue then
+                    // a^((q+3)/8) squares to either a or -a, and in the latter case
+                    // multiplying it by a square root of -1 gives the result.
+
+                    let mut a1 = self.pow_vartime(#mod_plus_3_over_8);
+
+                    let mut a0 = a1;
+                    a0.square();
+
+                    if a0 == *self {
+                        return Some(a1);
+                    }
+
+                    a1.mul_assign(&#name { #field: #repr(#sqrt_minus_one) });
+
+                    let mut a0 = a1;
+                    a0.square();
+
+                    if a0 == *self {
+                        Some(a1)
+                    } else {
+                        None
+                    }
+                }
+            }
+        }
+    } else {
+        // The remaining moduli are 1 mod 8, which the general algorithm handles.
         let t_plus_1_over_2 = biguint_to_u64_vec((&t + BigUint::one()) >> 1, limbs);
-        let t = biguint_to_u64_vec(t.clone(), limbs);
 
         quote!{
             impl ::ff::SqrtField for #name {
                 fn sqrt(&self) -> Option<Self> {
-                    // Tonelli-Shank's algorithm for q mod 16 = 1
+                    // Tonelli-Shank's algorithm for q mod 8 = 1
                     // https://eprint.iacr.org/2012/685.pdf (page 12, algorithm 5)
 
                     if self.is_zero() {
                         return Some(*self);
                     }
 
-                    if self.pow(#mod_minus_1_over_2) != Self::one() {
+                    if self.pow_vartime(MODULUS_MINUS_1_OVER_2) != Self::one() {
                         None
                     } else {
-                        let mut c = #name(#repr(#root_of_unity));
-                        let mut r = self.pow(#t_plus_1_over_2);
-                        let mut t = self.pow(#t);
+                        let mut c = #name { #field: #repr(#root_of_unity) };
+                        let mut r = self.pow_vartime(#t_plus_1_over_2);
+                        let mut t = self.pow_vartime(T);
                         let mut m = #s;
 
                         while t != Self::one() {
@@ -443,13 +679,19 @@ fn prime_field_constants_and_sqrt(
                 }
             }
         }
-    } else {
-        quote!{}
     };
 
+    let t = biguint_to_u64_vec(t, limbs);
+
     // Compute R^2 mod m
     let r2 = biguint_to_u64_vec((&r * &r) % &modulus, limbs);
 
+    // Compute the element 2**(64 * limbs), which is R^2 unless this is a small field
+    let wide_shift = biguint_to_u64_vec((((BigUint::one() << (limbs * 64)) % &modulus) * &r) % &modulus, limbs);
+
+    // Compute the element -1, which is m - R
+    let minus_one = biguint_to_u64_vec(&modulus - &r, limbs);
+
     let r = biguint_to_u64_vec(r, limbs);
     let modulus = biguint_to_u64_vec(modulus, limbs);
 
@@ -461,6 +703,25 @@ fn prime_field_constants_and_sqrt(
     }
     inv = inv.wrapping_neg();
 
+    let words = match limb_size {
+        LimbSize::U32 => {
+            let modulus32: Vec<u32> = modulus.iter()
+                                             .flat_map(|&l| vec![l as u32, (l >> 32) as u32])
+                                             .collect();
+            let words = modulus32.len();
+            let inv32 = inv as u32;
+
+            quote! {
+                /// The modulus m as 32-bit words, least significant first
+                const MODULUS32: [u32; #words] = #modulus32;
+
+                /// -(m^{-1} mod m) mod 2^32
+                const INV32: u32 = #inv32;
+            }
+        },
+        LimbSize::U64 => quote!{}
+    };
+
     quote! {
         /// This is the modulus m of the prime field
         const MODULUS: #repr = #repr(#modulus);
@@ -478,6 +739,12 @@ fn prime_field_constants_and_sqrt(
         /// 2^{limbs*64*2} mod m
         const R2: #repr = #repr(#r2);
 
+        /// The element -1
+        const MINUS_ONE: #repr = #repr(#minus_one);
+
+        /// The element 2^{limbs*64}, for combining the halves of double-width values
+        const WIDE_SHIFT: #repr = #repr(#wide_shift);
+
         /// -(m^{-1} mod m) mod m
         const INV: u64 = #inv;
 
@@ -488,18 +755,252 @@ fn prime_field_constants_and_sqrt(
         /// 2^s * t = MODULUS - 1 with t odd
         const S: usize = #s;
 
+        /// t for 2^s * t = MODULUS - 1 with t odd
+        const T: #repr = #repr(#t);
+
+        /// (t - 1) / 2
+        const T_MINUS_1_OVER_2: #repr = #repr(#t_minus_1_over_2);
+
+        /// (MODULUS - 1) / 2
+        const MODULUS_MINUS_1_OVER_2: #repr = #repr(#modulus_minus_1_over_2);
+
         /// 2^s root of unity computed by GENERATOR^t
         const ROOT_OF_UNITY: #repr = #repr(#root_of_unity);
 
+        #words
+
         #sqrt_impl
     }
 }
 
+/// Implement the arithmetic operators for the field `name`, for both values and
+/// references, in terms of the `Field` methods, along with `Sum` and `Product`.
+fn prime_field_ops_impl(
+    name: &syn::Ident
+) -> quote::Tokens
+{
+    let mut gen = quote::Tokens::new();
+
+    for &(op, method, op_assign, method_assign) in &[
+        ("Add", "add", "AddAssign", "add_assign"),
+        ("Sub", "sub", "SubAssign", "sub_assign"),
+        ("Mul", "mul", "MulAssign", "mul_assign")
+    ] {
+        let op = syn::Ident::from(op);
+        let method = syn::Ident::from(method);
+        let op_assign = syn::Ident::from(op_assign);
+        let method_assign = syn::Ident::from(method_assign);
+
+        gen.append(quote!{
+            impl<'r> ::std::ops::#op_assign<&'r #name> for #name {
+                #[inline]
+                fn #method_assign(&mut self, other: &'r #name) {
+                    ::ff::Field::#method_assign(self, other);
+                }
+            }
+
+            impl ::std::ops::#op_assign for #name {
+                #[inline]
+                fn #method_assign(&mut self, other: #name) {
+                    ::ff::Field::#method_assign(self, &other);
+                }
+            }
+
+            impl<'r> ::std::ops::#op<&'r #name> for #name {
+                type Output = #name;
+
+                #[inline]
+                fn #method(mut self, other: &'r #name) -> #name {
+                    ::ff::Field::#method_assign(&mut self, other);
+                    self
+                }
+            }
+
+            impl ::std::ops::#op for #name {
+                type Output = #name;
+
+                #[inline]
+                fn #method(mut self, other: #name) -> #name {
+                    ::ff::Field::#method_assign(&mut self, &other);
+                    self
+                }
+            }
+
+            impl<'a, 'r> ::std::ops::#op<&'r #name> for &'a #name {
+                type Output = #name;
+
+                #[inline]
+                fn #method(self, other: &'r #name) -> #name {
+                    let mut res = *self;
+                    ::ff::Field::#method_assign(&mut res, other);
+                    res
+                }
+            }
+
+            impl<'a> ::std::ops::#op<#name> for &'a #name {
+                type Output = #name;
+
+                #[inline]
+                fn #method(self, other: #name) -> #name {
+                    let mut res = *self;
+                    ::ff::Field::#method_assign(&mut res, &other);
+                    res
+                }
+            }
+        });
+    }
+
+    gen.append(quote!{
+        impl From<u64> for #name {
+            #[inline]
+            fn from(val: u64) -> #name {
+                ::ff::PrimeField::from_u64(val)
+            }
+        }
+
+        impl ::std::ops::Neg for #name {
+            type Output = #name;
+
+            #[inline]
+            fn neg(mut self) -> #name {
+                ::ff::Field::negate(&mut self);
+                self
+            }
+        }
+
+        impl<'a> ::std::ops::Neg for &'a #name {
+            type Output = #name;
+
+            #[inline]
+            fn neg(self) -> #name {
+                let mut res = *self;
+                ::ff::Field::negate(&mut res);
+                res
+            }
+        }
+
+        impl ::std::iter::Sum for #name {
+            fn sum<I: Iterator<Item = #name>>(iter: I) -> #name {
+                iter.fold(::ff::Field::zero(), |acc, x| acc + x)
+            }
+        }
+
+        impl<'r> ::std::iter::Sum<&'r #name> for #name {
+            fn sum<I: Iterator<Item = &'r #name>>(iter: I) -> #name {
+                iter.fold(::ff::Field::zero(), |acc, x| acc + x)
+            }
+        }
+
+        impl ::std::iter::Product for #name {
+            fn product<I: Iterator<Item = #name>>(iter: I) -> #name {
+                iter.fold(::ff::Field::one(), |acc, x| acc * x)
+            }
+        }
+
+        impl<'r> ::std::iter::Product<&'r #name> for #name {
+            fn product<I: Iterator<Item = &'r #name>>(iter: I) -> #name {
+                iter.fold(::ff::Field::one(), |acc, x| acc * x)
+            }
+        }
+    });
+
+    gen
+}
+
+/// Implement Zeroize for the field `name` and its representation `repr`. The field
+/// is `Copy`, so it cannot zeroize itself on drop; secrets should be wrapped in
+/// `zeroize::Zeroizing` instead.
+fn prime_field_zeroize_impl(
+    name: &syn::Ident,
+    repr: &syn::Ident,
+    field: &syn::Ident
+) -> quote::Tokens
+{
+    quote!{
+        impl ::ff::zeroize::Zeroize for #repr {
+            fn zeroize(&mut self) {
+                ::ff::zeroize::Zeroize::zeroize(&mut self.0);
+            }
+        }
+
+        impl ::ff::zeroize::Zeroize for #name {
+            fn zeroize(&mut self) {
+                ::ff::zeroize::Zeroize::zeroize(&mut self.#field);
+            }
+        }
+    }
+}
+
+/// Implement serde's Serialize and Deserialize for the field `name`, as the canonical
+/// big-endian bytes of the representation. Human-readable formats use a hex string.
+fn prime_field_serde_impl(
+    name: &syn::Ident,
+    limbs: usize
+) -> quote::Tokens
+{
+    quote!{
+        impl ::ff::serde::Serialize for #name {
+            fn serialize<S: ::ff::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut bytes = Vec::with_capacity(#limbs * 8);
+                for limb in self.into_repr().0.iter().rev() {
+                    bytes.extend_from_slice(&limb.to_be_bytes());
+                }
+
+                if serializer.is_human_readable() {
+                    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                    serializer.serialize_str(&hex)
+                } else {
+                    serializer.serialize_bytes(&bytes)
+                }
+            }
+        }
+
+        impl<'de> ::ff::serde::Deserialize<'de> for #name {
+            fn deserialize<D: ::ff::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct Visitor;
+
+                impl<'de> ::ff::serde::de::Visitor<'de> for Visitor {
+                    type Value = #name;
+
+                    fn expecting(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                        write!(f, "the canonical big-endian encoding of a {} element", stringify!(#name))
+                    }
+
+                    fn visit_str<E: ::ff::serde::de::Error>(self, v: &str) -> Result<#name, E> {
+                        if v.len() % 2 != 0 || !v.bytes().all(|c| c.is_ascii_hexdigit()) {
+                            return Err(E::invalid_value(::ff::serde::de::Unexpected::Str(v), &self));
+                        }
+
+                        let bytes: Vec<u8> = (0..v.len()).step_by(2)
+                            .map(|i| u8::from_str_radix(&v[i..i + 2], 16).unwrap())
+                            .collect();
+                        self.visit_bytes(&bytes)
+                    }
+
+                    fn visit_bytes<E: ::ff::serde::de::Error>(self, v: &[u8]) -> Result<#name, E> {
+                        <#name as ::ff::PrimeField>::from_bytes(v, ::ff::ByteOrder::BigEndian).map_err(E::custom)
+                    }
+                }
+
+                if deserializer.is_human_readable() {
+                    deserializer.deserialize_str(Visitor)
+                } else {
+                    deserializer.deserialize_bytes(Visitor)
+                }
+            }
+        }
+    }
+}
+
 /// Implement PrimeField for the derived type.
 fn prime_field_impl(
     name: &syn::Ident,
     repr: &syn::Ident,
-    limbs: usize
+    field: &syn::Ident,
+    modulus: &BigUint,
+    limbs: usize,
+    limb_size: LimbSize,
+    small_field: bool
 ) -> quote::Tokens
 {
     // Returns r{n} as an ident.
@@ -507,117 +1008,165 @@ fn prime_field_impl(
         syn::Ident::from(format!("r{}", n))
     }
 
-    // The parameter list for the mont_reduce() internal method.
+    // Returns the i-th machine word of the representation a, least significant first.
+    fn get_word(a: &quote::Tokens, i: usize, limb_size: LimbSize) -> quote::Tokens {
+        match limb_size {
+            LimbSize::U64 => quote!{ (#a).0[#i] },
+            LimbSize::U32 => {
+                let limb = i / 2;
+                if i % 2 == 0 {
+                    quote!{ ((#a).0[#limb] as u32) }
+                } else {
+                    quote!{ (((#a).0[#limb] >> 32) as u32) }
+                }
+            }
+        }
+    }
+
+    let words = limb_size.words(limbs);
+    let word_ty = limb_size.ty();
+
+    // The parameter list for the Montgomery reduction internal method.
     // r0: u64, mut r1: u64, mut r2: u64, ...
     let mut mont_paramlist = quote::Tokens::new();
     mont_paramlist.append_separated(
-        (0..(limbs*2)).map(|i| (i, get_temp(i)))
+        (0..(words*2)).map(|i| (i, get_temp(i)))
                .map(|(i, x)| {
                     if i != 0 {
-                        quote!{mut #x: u64}
+                        quote!{mut #x: #word_ty}
                     } else {
-                        quote!{#x: u64}
+                        quote!{#x: #word_ty}
                     }
                 }),
         ","
     );
 
-    // Implement montgomery reduction for some number of limbs
-    fn mont_impl(limbs: usize) -> quote::Tokens
+    // Implement montgomery reduction for some number of words
+    fn mont_impl(field: &syn::Ident, words: usize, limb_size: LimbSize) -> quote::Tokens
     {
+        let mac = limb_size.mac();
+        let adc = limb_size.adc();
+        let inv = limb_size.inv();
+        let modulus = limb_size.modulus();
+
         let mut gen = quote::Tokens::new();
 
-        for i in 0..limbs {
+        for i in 0..words {
             {
                 let temp = get_temp(i);
                 gen.append(quote!{
-                    let k = #temp.wrapping_mul(INV);
+                    let k = #temp.wrapping_mul(#inv);
                     let mut carry = 0;
-                    ::ff::mac_with_carry(#temp, k, MODULUS.0[0], &mut carry);
+                    #mac(#temp, k, #modulus[0], &mut carry);
                 });
             }
 
-            for j in 1..limbs {
+            for j in 1..words {
                 let temp = get_temp(i + j);
                 gen.append(quote!{
-                    #temp = ::ff::mac_with_carry(#temp, k, MODULUS.0[#j], &mut carry);
+                    #temp = #mac(#temp, k, #modulus[#j], &mut carry);
                 });
             }
 
-            let temp = get_temp(i + limbs);
+            let temp = get_temp(i + words);
 
             if i == 0 {
                 gen.append(quote!{
-                    #temp = ::ff::adc(#temp, 0, &mut carry);
+                    #temp = #adc(#temp, 0, &mut carry);
                 });
             } else {
                 gen.append(quote!{
-                    #temp = ::ff::adc(#temp, carry2, &mut carry);
+                    #temp = #adc(#temp, carry2, &mut carry);
                 });
             }
 
-            if i != (limbs - 1) {
+            if i != (words - 1) {
                 gen.append(quote!{
                     let carry2 = carry;
                 });
             }
         }
 
-        for i in 0..limbs {
-            let temp = get_temp(limbs + i);
+        match limb_size {
+            LimbSize::U64 => {
+                for i in 0..words {
+                    let temp = get_temp(words + i);
 
-            gen.append(quote!{
-                (self.0).0[#i] = #temp;
-            });
+                    gen.append(quote!{
+                        (self.#field).0[#i] = #temp;
+                    });
+                }
+            },
+            LimbSize::U32 => {
+                for i in 0..(words / 2) {
+                    let lo = get_temp(words + 2 * i);
+                    let hi = get_temp(words + 2 * i + 1);
+
+                    gen.append(quote!{
+                        (self.#field).0[#i] = (#lo as u64) | ((#hi as u64) << 32);
+                    });
+                }
+            }
         }
 
         gen
     }
 
-    fn sqr_impl(a: quote::Tokens, limbs: usize) -> quote::Tokens
+    fn sqr_impl(a: quote::Tokens, words: usize, limb_size: LimbSize) -> quote::Tokens
     {
+        if words == 1 {
+            // There are no cross terms to double, so this is just a multiplication.
+            return mul_impl(a.clone(), a, words, limb_size);
+        }
+
+        let mac = limb_size.mac();
+        let adc = limb_size.adc();
+        let shift = limb_size.bits() - 1;
+
         let mut gen = quote::Tokens::new();
 
-        for i in 0..(limbs-1) {
+        for i in 0..(words-1) {
             gen.append(quote!{
                 let mut carry = 0;
             });
 
-            for j in (i+1)..limbs {
+            for j in (i+1)..words {
                 let temp = get_temp(i + j);
+                let ai = get_word(&a, i, limb_size);
+                let aj = get_word(&a, j, limb_size);
                 if i == 0 {
                     gen.append(quote!{
-                        let #temp = ::ff::mac_with_carry(0, (#a.0).0[#i], (#a.0).0[#j], &mut carry);
+                        let #temp = #mac(0, #ai, #aj, &mut carry);
                     });
                 } else {
                     gen.append(quote!{
-                        let #temp = ::ff::mac_with_carry(#temp, (#a.0).0[#i], (#a.0).0[#j], &mut carry);
+                        let #temp = #mac(#temp, #ai, #aj, &mut carry);
                     });
                 }
             }
 
-            let temp = get_temp(i + limbs);
+            let temp = get_temp(i + words);
 
             gen.append(quote!{
                 let #temp = carry;
             });
         }
 
-        for i in 1..(limbs*2) {
+        for i in 1..(words*2) {
             let k = get_temp(i);
 
             if i == 1 {
                 gen.append(quote!{
-                    let tmp0 = #k >> 63;
+                    let tmp0 = #k >> #shift;
                     let #k = #k << 1;
                 });
-            } else if i == (limbs*2 - 1) {
+            } else if i == (words*2 - 1) {
                 gen.append(quote!{
                     let #k = tmp0;
                 });
             } else {
                 gen.append(quote!{
-                    let tmp1 = #k >> 63;
+                    let tmp1 = #k >> #shift;
                     let #k = #k << 1;
                     let #k = #k | tmp0;
                     let tmp0 = tmp1;
@@ -629,86 +1178,342 @@ fn prime_field_impl(
             let mut carry = 0;
         });
 
-        for i in 0..limbs {
+        for i in 0..words {
             let temp0 = get_temp(i * 2);
             let temp1 = get_temp(i * 2 + 1);
+            let ai = get_word(&a, i, limb_size);
             if i == 0 {
                 gen.append(quote!{
-                    let #temp0 = ::ff::mac_with_carry(0, (#a.0).0[#i], (#a.0).0[#i], &mut carry);
+                    let #temp0 = #mac(0, #ai, #ai, &mut carry);
                 });
             } else {
                 gen.append(quote!{
-                    let #temp0 = ::ff::mac_with_carry(#temp0, (#a.0).0[#i], (#a.0).0[#i], &mut carry);
+                    let #temp0 = #mac(#temp0, #ai, #ai, &mut carry);
                 });
             }
 
             gen.append(quote!{
-                let #temp1 = ::ff::adc(#temp1, 0, &mut carry);
+                let #temp1 = #adc(#temp1, 0, &mut carry);
             });
         }
 
+        let mont_reduce = limb_size.mont_reduce();
         let mut mont_calling = quote::Tokens::new();
-        mont_calling.append_separated((0..(limbs*2)).map(|i| get_temp(i)), ",");
+        mont_calling.append_separated((0..(words*2)).map(|i| get_temp(i)), ",");
 
         gen.append(quote!{
-            self.mont_reduce(#mont_calling);
+            self.#mont_reduce(#mont_calling);
         });
 
         gen
     }
 
-    fn mul_impl(a: quote::Tokens, b: quote::Tokens, limbs: usize) -> quote::Tokens
+    fn mul_impl(a: quote::Tokens, b: quote::Tokens, words: usize, limb_size: LimbSize) -> quote::Tokens
     {
+        let mac = limb_size.mac();
+
         let mut gen = quote::Tokens::new();
 
-        for i in 0..limbs {
+        for i in 0..words {
             gen.append(quote!{
                 let mut carry = 0;
             });
 
-            for j in 0..limbs {
+            for j in 0..words {
                 let temp = get_temp(i + j);
+                let ai = get_word(&a, i, limb_size);
+                let bj = get_word(&b, j, limb_size);
 
                 if i == 0 {
                     gen.append(quote!{
-                        let #temp = ::ff::mac_with_carry(0, (#a.0).0[#i], (#b.0).0[#j], &mut carry);
+                        let #temp = #mac(0, #ai, #bj, &mut carry);
                     });
                 } else {
                     gen.append(quote!{
-                        let #temp = ::ff::mac_with_carry(#temp, (#a.0).0[#i], (#b.0).0[#j], &mut carry);
+                        let #temp = #mac(#temp, #ai, #bj, &mut carry);
                     });
                 }
             }
 
-            let temp = get_temp(i + limbs);
+            let temp = get_temp(i + words);
 
             gen.append(quote!{
                 let #temp = carry;
             });
         }
 
+        let mont_reduce = limb_size.mont_reduce();
         let mut mont_calling = quote::Tokens::new();
-        mont_calling.append_separated((0..(limbs*2)).map(|i| get_temp(i)), ",");
+        mont_calling.append_separated((0..(words*2)).map(|i| get_temp(i)), ",");
 
         gen.append(quote!{
-            self.mont_reduce(#mont_calling);
+            self.#mont_reduce(#mont_calling);
         });
 
         gen
     }
 
-    let squaring_impl = sqr_impl(quote!{self}, limbs);
-    let multiply_impl = mul_impl(quote!{self}, quote!{other}, limbs);
-    let montgomery_impl = mont_impl(limbs);
+    // Sums of products accumulate the unreduced products over 64-bit limbs. With 32-bit
+    // words we keep the default implementation, which multiplies over words.
+    let sum_of_products_impl = match limb_size {
+        LimbSize::U64 => {
+            let limbs2 = limbs * 2;
+            let mut acc_args = quote::Tokens::new();
+            acc_args.append_separated((0..limbs2).map(|i| quote!{acc[#i]}), ",");
+
+            quote!{
+                fn sum_of_products(a: &[#name], b: &[#name]) -> #name
+                {
+                    assert_eq!(a.len(), b.len());
+
+                    // Accumulate the unreduced products, keeping the upper half below the
+                    // modulus so that the sum remains a valid input to the Montgomery reduction.
+                    let mut acc = [0u64; #limbs2];
+                    for (a, b) in a.iter().zip(b) {
+                        let mut prod = [0u64; #limbs2];
+                        for (i, ai) in (a.#field).0.iter().enumerate() {
+                            let mut carry = 0;
+                            for (j, bj) in (b.#field).0.iter().enumerate() {
+                                prod[i + j] = ::ff::mac_with_carry(prod[i + j], *ai, *bj, &mut carry);
+                            }
+                            prod[i + #limbs] = carry;
+                        }
+
+                        // This cannot exceed the accumulator, as 2 * modulus < R.
+                        let mut carry = 0;
+                        for (x, y) in acc.iter_mut().zip(prod.iter()) {
+                            *x = ::ff::adc(*x, *y, &mut carry);
+                        }
+
+                        // Subtract the modulus from the upper half, keeping the original
+                        // limbs iff that borrowed, without branching on the result.
+                        let mut hi = #repr::default();
+                        hi.0.copy_from_slice(&acc[#limbs..]);
+                        let borrow = hi.sub_noborrow(&MODULUS);
+                        let mask = 0u64.wrapping_sub(borrow as u64);
+                        for (a, b) in acc[#limbs..].iter_mut().zip(hi.0.iter()) {
+                            *a = (*a & mask) | (b & !mask);
+                        }
+                    }
+
+                    let mut res = #name::zero();
+                    res.mont_reduce(#acc_args);
+                    res
+                }
+            }
+        },
+        LimbSize::U32 => quote!{}
+    };
+
+    // Multiplying by a u64 leaves a product below m * 2^64, which is reduced by
+    // subtracting an estimate of its quotient by m.
+    let mul_by_u64_impl = if limbs == 1 {
+        quote!{
+            fn mul_by_u64(&mut self, scalar: u64)
+            {
+                let wide = ((self.#field).0[0] as u128) * (scalar as u128);
+                (self.#field).0[0] = (wide % (MODULUS.0[0] as u128)) as u64;
+            }
+        }
+    } else {
+        // Bits e..e+128 of the product, where e + 64 is the bit length of m. As
+        // the product is below m * 2^64, this is all of its high bits.
+        let e = biguint_num_bits(modulus.clone()) as usize - 64;
+        let (w, b) = (e / 64, e % 64);
+        let top = if b == 0 {
+            let w1 = w + 1;
+            quote!{ ((prod[#w1] as u128) << 64) | (prod[#w] as u128) }
+        } else {
+            let (w1, w2) = (w + 1, w + 2);
+            let (b1, b2) = (64 - b, 128 - b);
+            quote!{
+                ((prod[#w2] as u128) << #b2) | ((prod[#w1] as u128) << #b1) | ((prod[#w] as u128) >> #b)
+            }
+        };
+        let modulus_top = biguint_to_u64_vec(modulus.clone() >> e, 1)[0];
+        let limbs1 = limbs + 1;
+
+        quote!{
+            fn mul_by_u64(&mut self, scalar: u64)
+            {
+                let mut prod = [0u64; #limbs1];
+                let mut carry = 0;
+                for (x, a) in prod.iter_mut().zip((self.#field).0.iter()) {
+                    *x = ::ff::mac_with_carry(0, *a, scalar, &mut carry);
+                }
+                prod[#limbs] = carry;
+
+                // Dividing the high bits of the product by the high bits of m, rounded
+                // up, underestimates the quotient by at most 3, as the top of the high
+                // bits of m is set.
+                let q = ((#top) / (#modulus_top as u128 + 1)) as u64;
+
+                let mut carry = 0;
+                let mut borrow = 0;
+                for i in 0..#limbs {
+                    let qm = ::ff::mac_with_carry(0, MODULUS.0[i], q, &mut carry);
+                    prod[i] = ::ff::sbb(prod[i], qm, &mut borrow);
+                }
+                prod[#limbs] = ::ff::sbb(prod[#limbs], carry, &mut borrow);
+
+                // The remainder is now below 4 * m; subtract m up to three times,
+                // keeping each result iff it did not borrow.
+                for _ in 0..3 {
+                    let mut tmp = [0u64; #limbs1];
+                    let mut borrow = 0;
+                    for i in 0..#limbs {
+                        tmp[i] = ::ff::sbb(prod[i], MODULUS.0[i], &mut borrow);
+                    }
+                    tmp[#limbs] = ::ff::sbb(prod[#limbs], 0, &mut borrow);
+
+                    let mask = 0u64.wrapping_sub(borrow);
+                    for (a, b) in prod.iter_mut().zip(tmp.iter()) {
+                        *a = (*a & mask) | (b & !mask);
+                    }
+                }
+
+                (self.#field).0.copy_from_slice(&prod[..#limbs]);
+            }
+        }
+    };
+
+    // With the subtle feature, inversion by Fermat's little theorem runs the same
+    // operations for every element, including zero.
+    let invert_impl = if cfg!(feature = "subtle") {
+        let mut modulus_minus_2 = quote::Tokens::new();
+        modulus_minus_2.append_separated(
+            biguint_to_u64_vec(modulus - BigUint::from(2u64), limbs).into_iter(),
+            ","
+        );
+
+        quote!{
+            fn invert(&self) -> ::ff::subtle::CtOption<Self>
+            {
+                let nonzero = (self.#field).0.iter().fold(0, |acc, limb| acc | limb);
+                let is_some = ((nonzero | nonzero.wrapping_neg()) >> 63) as u8;
+
+                ::ff::subtle::CtOption::new(self.pow([#modulus_minus_2]), ::ff::subtle::Choice::from(is_some))
+            }
+        }
+    } else {
+        quote!{}
+    };
+
+    let squaring_impl = sqr_impl(quote!{self.#field}, words, limb_size);
+    let multiply_impl = mul_impl(quote!{self.#field}, quote!{other.#field}, words, limb_size);
+    let montgomery_impl = mont_impl(field, words, limb_size);
+
+    // The Montgomery reduction always accepts 64-bit limbs. With 32-bit words, it
+    // splits them and defers to the reduction over words.
+    let mont_reduce_impl = match limb_size {
+        LimbSize::U64 if small_field => {
+            quote!{
+                #[inline(always)]
+                fn mont_reduce(
+                    &mut self,
+                    r0: u64,
+                    r1: u64
+                )
+                {
+                    // With R = 1, this is a plain reduction of the double-width value.
+                    let wide = ((r1 as u128) << 64) | (r0 as u128);
+                    (self.#field).0[0] = (wide % (MODULUS.0[0] as u128)) as u64;
+                }
+            }
+        },
+        LimbSize::U64 => {
+            quote!{
+                #[allow(clippy::too_many_arguments)]
+                #[inline(always)]
+                fn mont_reduce(
+                    &mut self,
+                    #mont_paramlist
+                )
+                {
+                    // The Montgomery reduction here is based on Algorithm 14.32 in
+                    // Handbook of Applied Cryptography
+                    // <http://cacr.uwaterloo.ca/hac/about/chap14.pdf>.
+
+                    #montgomery_impl
+
+                    self.reduce();
+                }
+            }
+        },
+        LimbSize::U32 => {
+            let mut limb_paramlist = quote::Tokens::new();
+            limb_paramlist.append_separated(
+                (0..(limbs*2)).map(|i| {
+                    let x = get_temp(i);
+                    quote!{#x: u64}
+                }),
+                ","
+            );
+
+            let mut split_words = quote::Tokens::new();
+            split_words.append_separated(
+                (0..(limbs*2)).map(|i| {
+                    let x = get_temp(i);
+                    quote!{#x as u32, (#x >> 32) as u32}
+                }),
+                ","
+            );
+
+            quote!{
+                #[allow(clippy::too_many_arguments)]
+                #[inline(always)]
+                fn mont_reduce(
+                    &mut self,
+                    #limb_paramlist
+                )
+                {
+                    self.mont_reduce32(#split_words);
+                }
+
+                #[allow(clippy::too_many_arguments)]
+                #[inline(always)]
+                fn mont_reduce32(
+                    &mut self,
+                    #mont_paramlist
+                )
+                {
+                    // The Montgomery reduction here is based on Algorithm 14.32 in
+                    // Handbook of Applied Cryptography
+                    // <http://cacr.uwaterloo.ca/hac/about/chap14.pdf>.
+
+                    #montgomery_impl
+
+                    self.reduce();
+                }
+            }
+        }
+    };
 
     // (self.0).0[0], (self.0).0[1], ..., 0, 0, 0, 0, ...
     let mut into_repr_params = quote::Tokens::new();
     into_repr_params.append_separated(
-        (0..limbs).map(|i| quote!{ (self.0).0[#i] })
+        (0..limbs).map(|i| quote!{ (self.#field).0[#i] })
                   .chain((0..limbs).map(|_| quote!{0})),
         ","
     );
 
+    // The unused bits at the top of the representation can span the whole top limb.
+    let top_limb = limbs - 1;
+
+    // Small fields are not kept in Montgomery form, which amounts to R = 1.
+    let expected_r = if small_field {
+        quote!{ #repr::from(1) }
+    } else {
+        quote!{{
+            let mut wide = #repr::from(1);
+            for _ in 0..(64 * #limbs) {
+                wide = add_mod(wide, &wide.clone());
+            }
+            wide
+        }}
+    };
+    let top_limb_mask = (!0u64).checked_shr(64 * limbs as u32 - biguint_num_bits(modulus.clone())).unwrap_or(0);
+
     quote!{
         impl Copy for #name { }
 
@@ -720,12 +1525,41 @@ fn prime_field_impl(
 
         impl PartialEq for #name {
             fn eq(&self, other: &#name) -> bool {
-                self.0 == other.0
+                self.#field == other.#field
             }
         }
 
         impl Eq for #name { }
 
+        impl<'a> ::std::convert::TryFrom<&'a [u8]> for #name {
+            type Error = ::ff::PrimeFieldDecodingError;
+
+            fn try_from(bytes: &'a [u8]) -> Result<#name, ::ff::PrimeFieldDecodingError> {
+                #name::from_canonical_bytes(bytes)
+            }
+        }
+
+        /// Elements are ordered by their canonical representations, not by their
+        /// Montgomery forms.
+        impl Ord for #name {
+            fn cmp(&self, other: &#name) -> ::std::cmp::Ordering {
+                self.into_repr().cmp(&other.into_repr())
+            }
+        }
+
+        impl PartialOrd for #name {
+            fn partial_cmp(&self, other: &#name) -> Option<::std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl ::std::hash::Hash for #name {
+            fn hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+                // Hash the canonical representation, so that equal elements hash equally.
+                ::std::hash::Hash::hash(&self.into_repr().0, state);
+            }
+        }
+
         impl ::std::fmt::Debug for #name
         {
             fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
@@ -733,42 +1567,132 @@ fn prime_field_impl(
             }
         }
 
-        impl ::rand::Rand for #name {
-            /// Computes a uniformly random element using rejection sampling.
-            fn rand<R: ::rand::Rng>(rng: &mut R) -> Self {
+        impl ::std::fmt::LowerHex for #name
+        {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                if f.alternate() {
+                    try!(write!(f, "0x"));
+                }
+                for i in self.into_repr().0.iter().rev() {
+                    try!(write!(f, "{:016x}", *i));
+                }
+
+                Ok(())
+            }
+        }
+
+        impl ::std::fmt::UpperHex for #name
+        {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                if f.alternate() {
+                    try!(write!(f, "0x"));
+                }
+                for i in self.into_repr().0.iter().rev() {
+                    try!(write!(f, "{:016X}", *i));
+                }
+
+                Ok(())
+            }
+        }
+
+        impl ::std::fmt::Display for #name
+        {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                // Repeatedly divide by 10^19, the largest power of ten that fits in a limb,
+                // collecting the decimal digits in chunks of 19.
+                let mut repr = self.into_repr();
+                let mut chunks = vec![];
                 loop {
-                    let mut tmp = #name(#repr::rand(rng));
-                    for _ in 0..REPR_SHAVE_BITS {
-                        tmp.0.div2();
+                    let mut rem = 0u128;
+                    for limb in repr.0.iter_mut().rev() {
+                        let cur = (rem << 64) | (*limb as u128);
+                        *limb = (cur / 10_000_000_000_000_000_000) as u64;
+                        rem = cur % 10_000_000_000_000_000_000;
                     }
-                    if tmp.is_valid() {
-                        return tmp
+                    chunks.push(rem as u64);
+
+                    if repr.is_zero() {
+                        break;
                     }
                 }
+
+                try!(write!(f, "{}", chunks.pop().unwrap()));
+                for chunk in chunks.iter().rev() {
+                    try!(write!(f, "{:019}", chunk));
+                }
+
+                Ok(())
+            }
+        }
+
+        impl ::rand::Rand for #name {
+            fn rand<R: ::rand::Rng>(rng: &mut R) -> Self {
+                ::ff::Field::random(&mut ::ff::RngCompat(rng))
             }
         }
 
         impl ::ff::PrimeField for #name {
             type Repr = #repr;
 
-            fn from_repr(r: #repr) -> Result<#name, ()> {
-                let mut r = #name(r);
+            const MODULUS: #repr = MODULUS;
+
+            const NUM_BITS: u32 = MODULUS_BITS;
+
+            const CAPACITY: u32 = MODULUS_BITS - 1;
+
+            fn from_repr(r: #repr) -> Result<#name, ::ff::PrimeFieldDecodingError> {
+                let mut r = #name { #field: r };
                 if r.is_valid() {
-                    r.mul_assign(&#name(R2));
+                    // Zero is its own Montgomery form.
+                    if !r.is_zero() {
+                        r.mul_assign(&#name { #field: R2 });
+                    }
 
                     Ok(r)
                 } else {
-                    Err(())
+                    Err(::ff::PrimeFieldDecodingError::NotInField)
                 }
             }
 
+            fn from_u64(val: u64) -> #name {
+                // This is below modulus * R even when val exceeds the modulus, so the
+                // Montgomery reduction fully reduces it.
+                let mut r = #name { #field: #repr::from(val) };
+                r.mul_assign(&#name { #field: R2 });
+
+                r
+            }
+
+            fn reduce_double_width(lo: #repr, hi: #repr) -> #name {
+                // Each half is below R, so multiplying it by R2 is a valid Montgomery
+                // multiplication even when it is not reduced.
+                let mut lo = #name { #field: lo };
+                lo.mul_assign(&#name { #field: R2 });
+                let mut hi = #name { #field: hi };
+                hi.mul_assign(&#name { #field: R2 });
+
+                hi.mul_assign(&#name { #field: WIDE_SHIFT });
+                lo.add_assign(&hi);
+
+                lo
+            }
+
             fn into_repr(&self) -> #repr {
                 let mut r = *self;
                 r.mont_reduce(
                     #into_repr_params
                 );
 
-                r.0
+                r.#field
+            }
+
+            fn write_repr_into(&self, buf: &mut #repr) {
+                let mut r = *self;
+                r.mont_reduce(
+                    #into_repr_params
+                );
+
+                *buf = r.#field;
             }
 
             fn char() -> #repr {
@@ -776,15 +1700,15 @@ fn prime_field_impl(
             }
 
             fn num_bits() -> u32 {
-                MODULUS_BITS
+                Self::NUM_BITS
             }
 
             fn capacity() -> u32 {
-                Self::num_bits() - 1
+                Self::CAPACITY
             }
 
             fn multiplicative_generator() -> Self {
-                #name(GENERATOR)
+                #name { #field: GENERATOR }
             }
 
             fn s() -> usize {
@@ -792,30 +1716,62 @@ fn prime_field_impl(
             }
 
             fn root_of_unity() -> Self {
-                #name(ROOT_OF_UNITY)
+                #name { #field: ROOT_OF_UNITY }
+            }
+
+            fn t() -> #repr {
+                T
+            }
+
+            fn t_minus_1_over_2() -> #repr {
+                T_MINUS_1_OVER_2
+            }
+
+            fn modulus_minus_one_over_two() -> #repr {
+                MODULUS_MINUS_1_OVER_2
             }
         }
 
         impl ::ff::Field for #name {
             #[inline]
             fn zero() -> Self {
-                #name(#repr::from(0))
+                #name { #field: #repr::from(0) }
+            }
+
+            /// Computes a uniformly random element using rejection sampling.
+            fn random<R: ::ff::rand_core::RngCore + ?Sized>(rng: &mut R) -> Self {
+                loop {
+                    if let Some(tmp) = #name::random_candidate(rng) {
+                        return tmp
+                    }
+                }
+            }
+
+            /// Computes a uniformly random element using at most `max_attempts`
+            /// rounds of rejection sampling.
+            fn random_bounded<R: ::ff::rand_core::RngCore + ?Sized>(rng: &mut R, max_attempts: usize) -> Option<Self> {
+                (0..max_attempts).filter_map(|_| #name::random_candidate(rng)).next()
             }
 
             #[inline]
             fn one() -> Self {
-                #name(R)
+                #name { #field: R }
+            }
+
+            #[inline]
+            fn minus_one() -> Self {
+                #name { #field: MINUS_ONE }
             }
 
             #[inline]
             fn is_zero(&self) -> bool {
-                self.0.is_zero()
+                self.#field.is_zero()
             }
 
             #[inline]
             fn add_assign(&mut self, other: &#name) {
                 // This cannot exceed the backing capacity.
-                self.0.add_nocarry(&other.0);
+                self.#field.add_nocarry(&other.#field);
 
                 // However, it may need to be reduced.
                 self.reduce();
@@ -824,7 +1780,7 @@ fn prime_field_impl(
             #[inline]
             fn double(&mut self) {
                 // This cannot exceed the backing capacity.
-                self.0.mul2();
+                self.#field.mul2();
 
                 // However, it may need to be reduced.
                 self.reduce();
@@ -832,20 +1788,39 @@ fn prime_field_impl(
 
             #[inline]
             fn sub_assign(&mut self, other: &#name) {
-                // If `other` is larger than `self`, we'll need to add the modulus to self first.
-                if other.0 > self.0 {
-                    self.0.add_nocarry(&MODULUS);
-                }
+                // If `other` is larger than `self`, the subtraction wraps around, and adding
+                // the modulus (ignoring the carry) wraps it back. The mask selects the modulus
+                // without branching on the borrow.
+                let borrow = self.#field.sub_noborrow(&other.#field);
+                let mask = 0u64.wrapping_sub(borrow as u64);
 
-                self.0.sub_noborrow(&other.0);
+                let mut carry = 0;
+                for (a, m) in (self.#field).0.iter_mut().zip(MODULUS.0.iter()) {
+                    *a = ::ff::adc(*a, m & mask, &mut carry);
+                }
             }
 
             #[inline]
             fn negate(&mut self) {
                 if !self.is_zero() {
                     let mut tmp = MODULUS;
-                    tmp.sub_noborrow(&self.0);
-                    self.0 = tmp;
+                    tmp.sub_noborrow(&self.#field);
+                    self.#field = tmp;
+                }
+            }
+
+            #[inline]
+            fn conditional_negate(&mut self, neg: bool) {
+                // Zero must be left unchanged, as the modulus is not reduced. The mask is
+                // all ones iff neg is set and this element is nonzero.
+                let nonzero = (self.#field).0.iter().fold(0, |acc, limb| acc | limb);
+                let nonzero = (nonzero | nonzero.wrapping_neg()) >> 63;
+                let mask = 0u64.wrapping_sub(neg as u64 & nonzero);
+
+                let mut tmp = MODULUS;
+                tmp.sub_noborrow(&self.#field);
+                for (a, b) in (self.#field).0.iter_mut().zip(tmp.0.iter()) {
+                    *a ^= (*a ^ b) & mask;
                 }
             }
 
@@ -859,31 +1834,31 @@ fn prime_field_impl(
 
                     let one = #repr::from(1);
 
-                    let mut u = self.0;
+                    let mut u = self.#field;
                     let mut v = MODULUS;
-                    let mut b = #name(R2); // Avoids unnecessary reduction step.
+                    let mut b = #name { #field: R2 }; // Avoids unnecessary reduction step.
                     let mut c = Self::zero();
 
                     while u != one && v != one {
                         while u.is_even() {
                             u.div2();
 
-                            if b.0.is_even() {
-                                b.0.div2();
+                            if b.#field.is_even() {
+                                b.#field.div2();
                             } else {
-                                b.0.add_nocarry(&MODULUS);
-                                b.0.div2();
+                                b.#field.add_nocarry(&MODULUS);
+                                b.#field.div2();
                             }
                         }
 
                         while v.is_even() {
                             v.div2();
 
-                            if c.0.is_even() {
-                                c.0.div2();
+                            if c.#field.is_even() {
+                                c.#field.div2();
                             } else {
-                                c.0.add_nocarry(&MODULUS);
-                                c.0.div2();
+                                c.#field.add_nocarry(&MODULUS);
+                                c.#field.div2();
                             }
                         }
 
@@ -905,8 +1880,11 @@ fn prime_field_impl(
             }
 
             #[inline(always)]
-            fn frobenius_map(&mut self, _: usize) {
-                // This has no effect in a prime field.
+            fn frobenius_map(&mut self, _power: usize) {
+                // A prime field has degree 1 over itself, so every power of the
+                // Frobenius automorphism is the identity, and the element is left
+                // as it is: already reduced, as the output of the map must be.
+                debug_assert!(self.is_valid(), "frobenius_map on an unreduced element");
             }
 
             #[inline]
@@ -920,39 +1898,200 @@ fn prime_field_impl(
             {
                 #squaring_impl
             }
+
+            fn pow<S: AsRef<[u64]>>(&self, exp: S) -> Self
+            {
+                let mut res = Self::one();
+
+                for limb in exp.as_ref().iter().rev() {
+                    for i in (0..64).rev() {
+                        res.square();
+                        let mut tmp = res;
+                        tmp.mul_assign(self);
+                        #name::conditional_swap(&mut res, &mut tmp, (limb >> i) & 1 == 1);
+                    }
+                }
+
+                res
+            }
+
+            fn pow_montgomery_ladder<S: AsRef<[u64]>>(&self, exp: S) -> Self
+            {
+                // Keeps r1 = r0 * self. Swapping the pair when the bit is set means
+                // that r0 is always the one squared.
+                let mut r0 = Self::one();
+                let mut r1 = *self;
+
+                for limb in exp.as_ref().iter().rev() {
+                    for i in (0..64).rev() {
+                        let bit = (limb >> i) & 1 == 1;
+
+                        #name::conditional_swap(&mut r0, &mut r1, bit);
+                        r1.mul_assign(&r0);
+                        r0.square();
+                        #name::conditional_swap(&mut r0, &mut r1, bit);
+                    }
+                }
+
+                r0
+            }
+
+            #sum_of_products_impl
+
+            #mul_by_u64_impl
+
+            #invert_impl
         }
 
         impl #name {
+            /// Constructs the element `val` in a `const` context. This is much slower
+            /// than `from_repr` and is meant for defining constants; see `field_const!`.
+            pub const fn from_u64_const(val: u64) -> #name {
+                // Double-and-add R over the bits of val, giving val * R mod m.
+                let mut acc = [0u64; #limbs];
+                let mut i = 64;
+                while i > 0 {
+                    i -= 1;
+                    acc = Self::const_add_mod(&acc, &acc);
+                    if (val >> i) & 1 == 1 {
+                        acc = Self::const_add_mod(&acc, &R.0);
+                    }
+                }
+
+                #name { #field: #repr(acc) }
+            }
+
+            /// Constructs the element from its canonical little-endian limbs. Fails if
+            /// the limbs do not encode a number less than the modulus.
+            pub fn from_u64s_le(limbs: [u64; #limbs]) -> Result<#name, ::ff::PrimeFieldDecodingError> {
+                ::ff::PrimeField::from_repr(#repr(limbs))
+            }
+
+            /// Checks the generated constants against the modulus using the runtime
+            /// arithmetic, returning a description of the first inconsistency found.
+            pub fn validate_parameters() -> Result<(), &'static str> {
+                // a + b mod m, for a, b < m. This cannot exceed the representation,
+                // as 2 * m < 2^{limbs*64}.
+                let add_mod = |mut a: #repr, b: &#repr| {
+                    a.add_nocarry(b);
+                    if a >= MODULUS {
+                        a.sub_noborrow(&MODULUS);
+                    }
+                    a
+                };
+
+                // a * b mod m, by double-and-add over the bits of b.
+                let mul_mod = |a: &#repr, b: &#repr| {
+                    let mut acc = #repr::from(0);
+                    for bit in ::ff::PrimeFieldRepr::bits(b) {
+                        acc = add_mod(acc, &acc.clone());
+                        if bit {
+                            acc = add_mod(acc, a);
+                        }
+                    }
+                    acc
+                };
+
+                if R != #expected_r {
+                    return Err("R is not 2^{limbs*64} mod m");
+                }
+                if R2 != mul_mod(&R, &R) {
+                    return Err("R2 is not R^2 mod m");
+                }
+                if INV.wrapping_mul(MODULUS.0[0]) != 0xffffffffffffffff {
+                    return Err("INV is not -(m^{-1}) mod 2^64");
+                }
+                if ::ff::PrimeField::legendre(&#name { #field: GENERATOR }) != ::ff::LegendreSymbol::QuadraticNonResidue {
+                    return Err("GENERATOR is not a quadratic nonresidue");
+                }
+
+                let root = #name { #field: ROOT_OF_UNITY };
+                if root.pow_2_pow(S as u32) != #name::one() || root.pow_2_pow(S as u32 - 1) == #name::one() {
+                    return Err("ROOT_OF_UNITY does not have order 2^S");
+                }
+
+                Ok(())
+            }
+
+            /// Computes a + b mod m for a, b < m in a `const` context. Only used
+            /// internally.
+            const fn const_add_mod(a: &[u64; #limbs], b: &[u64; #limbs]) -> [u64; #limbs] {
+                // This cannot exceed the backing capacity.
+                let mut sum = [0u64; #limbs];
+                let mut carry = 0;
+                let mut i = 0;
+                while i < #limbs {
+                    sum[i] = ::ff::adc(a[i], b[i], &mut carry);
+                    i += 1;
+                }
+
+                // However, it may need to be reduced.
+                let mut reduced = [0u64; #limbs];
+                let mut borrow = 0;
+                let mut i = 0;
+                while i < #limbs {
+                    reduced[i] = ::ff::sbb(sum[i], MODULUS.0[i], &mut borrow);
+                    i += 1;
+                }
+
+                if borrow == 0 {
+                    reduced
+                } else {
+                    sum
+                }
+            }
+
+            /// Performs a single round of rejection sampling, returning `None` if
+            /// the candidate was not in the field. This is only used internally.
+            fn random_candidate<R: ::ff::rand_core::RngCore + ?Sized>(rng: &mut R) -> Option<#name> {
+                let mut tmp = #name { #field: <#repr as ::ff::PrimeFieldRepr>::random(rng) };
+
+                // Mask away the unused bits at the top of the representation
+                (tmp.#field).0[#top_limb] &= #top_limb_mask;
+
+                if tmp.is_valid() {
+                    Some(tmp)
+                } else {
+                    None
+                }
+            }
+
+            /// Swaps `a` and `b` if `swap` is set, selecting the limbs with a mask
+            /// rather than branching on `swap`. This is only used internally.
+            #[inline(always)]
+            fn conditional_swap(a: &mut #name, b: &mut #name, swap: bool) {
+                let mask = 0u64.wrapping_sub(swap as u64);
+
+                for (a, b) in (a.#field).0.iter_mut().zip((b.#field).0.iter_mut()) {
+                    let t = (*a ^ *b) & mask;
+                    *a ^= t;
+                    *b ^= t;
+                }
+            }
+
             /// Determines if the element is really in the field. This is only used
             /// internally.
             #[inline(always)]
             fn is_valid(&self) -> bool {
-                self.0 < MODULUS
+                self.#field < MODULUS
             }
 
             /// Subtracts the modulus from this element if this element is not in the
             /// field. Only used interally.
             #[inline(always)]
             fn reduce(&mut self) {
-                if !self.is_valid() {
-                    self.0.sub_noborrow(&MODULUS);
+                // Always subtract the modulus, and keep the original limbs iff that
+                // borrowed, without branching on the result.
+                let mut tmp = self.#field;
+                let borrow = tmp.sub_noborrow(&MODULUS);
+                let mask = 0u64.wrapping_sub(borrow as u64);
+
+                for (a, b) in (self.#field).0.iter_mut().zip(tmp.0.iter()) {
+                    *a = (*a & mask) | (b & !mask);
                 }
             }
 
-            #[inline(always)]
-            fn mont_reduce(
-                &mut self,
-                #mont_paramlist
-            )
-            {
-                // The Montgomery reduction here is based on Algorithm 14.32 in
-                // Handbook of Applied Cryptography
-                // <http://cacr.uwaterloo.ca/hac/about/chap14.pdf>.
-
-                #montgomery_impl
-
-                self.reduce();
-            }
+            #mont_reduce_impl
         }
     }
 }
@@ -2,13 +2,31 @@
 #![allow(unused_imports)]
 
 extern crate rand;
+pub extern crate rand_core;
+
+#[cfg(feature = "serde")]
+pub extern crate serde;
+
+#[cfg(feature = "zeroize")]
+pub extern crate zeroize;
+
+#[cfg(feature = "bigint")]
+pub extern crate num_bigint;
+
+#[cfg(feature = "subtle")]
+pub extern crate subtle;
+
+#[cfg(feature = "digest")]
+pub extern crate digest;
 
 #[macro_use]
 extern crate ff_derive;
 
 pub use ff_derive::*;
 
+use std::cmp::Ordering;
 use std::fmt;
+use std::io::{self, Read, Write};
 
 /// This trait represents an element of a field.
 pub trait Field: Sized +
@@ -27,6 +45,51 @@ pub trait Field: Sized +
     /// Returns the one element of the field, the multiplicative identity.
     fn one() -> Self;
 
+    /// Returns the additive inverse of the one element.
+    fn minus_one() -> Self
+    {
+        let mut res = Self::one();
+        res.negate();
+        res
+    }
+
+    /// Returns the one element minus this element.
+    fn one_minus(&self) -> Self
+    {
+        let mut res = Self::one();
+        res.sub_assign(self);
+        res
+    }
+
+    /// Returns a uniformly random element of the field.
+    fn random<R: rand_core::RngCore + ?Sized>(rng: &mut R) -> Self;
+
+    /// Returns a uniformly random element of the field, or `None` if no
+    /// element was accepted within `max_attempts` sampling attempts.
+    ///
+    /// The default implementation makes a single call to `random` and so
+    /// only fails when `max_attempts` is zero; fields which sample by
+    /// rejection should override this to bound their loop.
+    fn random_bounded<R: rand_core::RngCore + ?Sized>(rng: &mut R, max_attempts: usize) -> Option<Self>
+    {
+        if max_attempts == 0 {
+            None
+        } else {
+            Some(Self::random(rng))
+        }
+    }
+
+    /// Returns a uniformly random nonzero element of the field.
+    fn random_nonzero<R: rand_core::RngCore + ?Sized>(rng: &mut R) -> Self
+    {
+        loop {
+            let tmp = Self::random(rng);
+            if !tmp.is_zero() {
+                return tmp;
+            }
+        }
+    }
+
     /// Returns true iff this element is zero.
     fn is_zero(&self) -> bool;
 
@@ -36,9 +99,45 @@ pub trait Field: Sized +
     /// Doubles this element.
     fn double(&mut self);
 
+    /// Returns the square of this element.
+    fn squared(&self) -> Self
+    {
+        let mut res = *self;
+        res.square();
+        res
+    }
+
+    /// Returns the cube of this element.
+    fn cube(&self) -> Self
+    {
+        let mut res = self.squared();
+        res.mul_assign(self);
+        res
+    }
+
+    /// Returns this element doubled.
+    fn doubled(&self) -> Self
+    {
+        let mut res = *self;
+        res.double();
+        res
+    }
+
     /// Negates this element.
     fn negate(&mut self);
 
+    /// Negates this element if `neg` is true. Derived fields do this without
+    /// branching on `neg`. This default selects the result by indexing with
+    /// `neg`, which the compiler may turn into a branch or a secret-dependent
+    /// load, so it is not constant time; implementations handling secrets
+    /// should override it with a masked select.
+    fn conditional_negate(&mut self, neg: bool)
+    {
+        let mut tmp = *self;
+        tmp.negate();
+        *self = [*self, tmp][neg as usize];
+    }
+
     /// Adds another element to this element.
     fn add_assign(&mut self, other: &Self);
 
@@ -48,19 +147,170 @@ pub trait Field: Sized +
     /// Multiplies another element by this element.
     fn mul_assign(&mut self, other: &Self);
 
+    /// Replaces `a` and `b` with `a + b` and `a - b`.
+    fn butterfly(a: &mut Self, b: &mut Self)
+    {
+        let tmp = *a;
+        a.add_assign(b);
+        b.negate();
+        b.add_assign(&tmp);
+    }
+
+    /// Replaces `a` and `b` with `a + b * twiddle` and `a - b * twiddle`, the
+    /// Cooley-Tukey butterfly.
+    fn butterfly_twiddle(a: &mut Self, b: &mut Self, twiddle: &Self)
+    {
+        b.mul_assign(twiddle);
+        Self::butterfly(a, b);
+    }
+
+    /// Multiplies this element by `scalar`.
+    fn mul_by_u64(&mut self, scalar: u64)
+    {
+        let mut res = Self::zero();
+        for i in (0..64).rev() {
+            res.double();
+            if (scalar >> i) & 1 == 1 {
+                res.add_assign(self);
+            }
+        }
+
+        *self = res;
+    }
+
+    /// Computes the sum of the products of the corresponding elements of `a` and
+    /// `b`. Panics if the slices differ in length.
+    ///
+    /// Derived fields over 64-bit words override this to accumulate the
+    /// unreduced products and reduce only once. Fields derived with
+    /// `PrimeFieldReprLimbSize = "32"` use this default, which reduces every
+    /// product.
+    fn sum_of_products(a: &[Self], b: &[Self]) -> Self
+    {
+        assert_eq!(a.len(), b.len());
+
+        let mut acc = Self::zero();
+        for (a, b) in a.iter().zip(b) {
+            let mut tmp = *a;
+            tmp.mul_assign(b);
+            acc.add_assign(&tmp);
+        }
+
+        acc
+    }
+
+    /// Computes the sum of the products of each pair, using `sum_of_products`.
+    fn sum_of_products_pairs(pairs: &[(Self, Self)]) -> Self
+    {
+        let (a, b): (Vec<Self>, Vec<Self>) = pairs.iter().cloned().unzip();
+
+        Self::sum_of_products(&a, &b)
+    }
+
     /// Computes the multiplicative inverse of this element, if nonzero.
     fn inverse(&self) -> Option<Self>;
 
+    /// Computes the multiplicative inverse of this element. Panics with `context`
+    /// and the element itself if the element is zero.
+    fn inverse_expect(&self, context: &str) -> Self
+    {
+        match self.inverse() {
+            Some(inv) => inv,
+            None => panic!("{}: cannot invert {:?}", context, self)
+        }
+    }
+
+    /// Computes the multiplicative inverse of this element, which is present
+    /// exactly when this element is nonzero. Implementations should not branch on
+    /// whether the element is zero; this default falls back to `inverse()`, and
+    /// derived fields override it.
+    #[cfg(feature = "subtle")]
+    fn invert(&self) -> subtle::CtOption<Self>
+    {
+        match self.inverse() {
+            Some(inv) => subtle::CtOption::new(inv, subtle::Choice::from(1)),
+            None => subtle::CtOption::new(Self::zero(), subtle::Choice::from(0))
+        }
+    }
+
+    /// Computes `numerator` divided by this element, if this element is nonzero.
+    fn invert_and_mul(&self, numerator: &Self) -> Option<Self>
+    {
+        self.inverse().map(|mut inv| {
+            inv.mul_assign(numerator);
+            inv
+        })
+    }
+
+    /// Fully reduces this element to its unique canonical representation. Callers
+    /// may rely on this before serializing or comparing elements. Implementations
+    /// which always keep their elements reduced need not override this.
+    fn canonicalize(&mut self) {}
+
     /// Exponentiates this element by a power of the base prime modulus via
-    /// the Frobenius automorphism.
+    /// the Frobenius automorphism, computing `self^(p^power)`. Any `power` is
+    /// valid: in a field of degree `k` over its prime subfield, the map has
+    /// order `k` and only `power % k` matters. In a prime field, every power
+    /// is the identity, so extension fields must not rely on the base field's
+    /// map to do any work.
     fn frobenius_map(&mut self, power: usize);
 
     /// Exponentiates this element by a number represented with `u64` limbs,
-    /// least significant digit first.
+    /// least significant digit first. This performs the same operations for
+    /// every exponent with the same number of limbs. Derived fields select the
+    /// intermediate results with a mask; this default selects them by indexing
+    /// with the exponent's bits, and implementations handling secret exponents
+    /// should override it.
     fn pow<S: AsRef<[u64]>>(&self, exp: S) -> Self
     {
         let mut res = Self::one();
 
+        for i in BitIterator::new(exp) {
+            res.square();
+            let mut tmp = res;
+            tmp.mul_assign(self);
+            res = [res, tmp][i as usize];
+        }
+
+        res
+    }
+
+    /// Exponentiates this element by a number represented with `u64` limbs,
+    /// least significant digit first, using a Montgomery ladder. This performs
+    /// exactly one squaring and one multiplication per bit of the exponent, on
+    /// two running values, so the operations do not depend on the bits. Derived
+    /// fields swap the running values with a mask; this default selects them by
+    /// indexing with the exponent's bits, and implementations handling secret
+    /// exponents should override it.
+    fn pow_montgomery_ladder<S: AsRef<[u64]>>(&self, exp: S) -> Self
+    {
+        // Keeps r1 = r0 * self.
+        let mut r0 = Self::one();
+        let mut r1 = *self;
+
+        for i in BitIterator::new(exp) {
+            let i = i as usize;
+
+            let mut prod = r0;
+            prod.mul_assign(&r1);
+            let mut sq = [r0, r1][i];
+            sq.square();
+
+            r0 = [sq, prod][i];
+            r1 = [prod, sq][i];
+        }
+
+        r0
+    }
+
+    /// Exponentiates this element by a number represented with `u64` limbs,
+    /// least significant digit first. This is faster than `pow`, but its running
+    /// time depends on the exponent, so it must only be used with public exponents.
+    /// The exponent may be longer than the field's representation.
+    fn pow_vartime<S: AsRef<[u64]>>(&self, exp: S) -> Self
+    {
+        let mut res = Self::one();
+
         for i in BitIterator::new(exp) {
             res.square();
             if i {
@@ -70,6 +320,49 @@ pub trait Field: Sized +
 
         res
     }
+
+    /// Squares each of the elements in place.
+    fn batch_square(elements: &mut [Self])
+    {
+        for e in elements {
+            e.square();
+        }
+    }
+
+    /// Computes `self^(2^k)` by squaring this element `k` times.
+    fn pow_2_pow(&self, k: u32) -> Self
+    {
+        let mut res = *self;
+
+        for _ in 0..k {
+            res.square();
+        }
+
+        res
+    }
+
+    /// Exponentiates this element by a signed exponent, inverting it first if the
+    /// exponent is negative. Returns `None` if this element is zero and the
+    /// exponent is negative.
+    fn pow_i64(&self, exp: i64) -> Option<Self>
+    {
+        let base = if exp < 0 {
+            self.inverse()?
+        } else {
+            *self
+        };
+
+        Some(base.pow([exp.unsigned_abs()]))
+    }
+
+    /// Samples two independent, uniformly random nonzero elements, for use in
+    /// tests of division.
+    #[cfg(feature = "test-util")]
+    fn random_nonzero_pair<R: rand_core::RngCore + ?Sized>(rng: &mut R) -> (Self, Self)
+    {
+        let a = Self::random_nonzero(rng);
+        (a, Self::random_nonzero(rng))
+    }
 }
 
 /// This trait represents an element of a field that has a square root operation described for it.
@@ -78,6 +371,69 @@ pub trait SqrtField: Field
     /// Returns the square root of the field element, if it is
     /// quadratic residue.
     fn sqrt(&self) -> Option<Self>;
+
+    /// Computes the square root of `num / div`, returning `(true, root)` if the
+    /// ratio is a square. Otherwise returns `(false, root)`, where `root` is the
+    /// square root of the ratio times `multiplicative_generator()`, a fixed
+    /// nonresidue, as hash-to-curve maps require. A zero `num` or `div` gives
+    /// `(true, 0)`.
+    ///
+    /// The division is folded into a single exponentiation, so no inversion is
+    /// performed. This is the generic `sqrt_ratio` of RFC 9380, appendix F.2.1;
+    /// when the modulus is 3 mod 4 it reduces to computing
+    /// `num * div * (num * div^3)^((p - 3)/4)`.
+    fn sqrt_ratio(num: &Self, div: &Self) -> (bool, Self)
+    where Self: PrimeField
+    {
+        let s = Self::s() as u32;
+        let z = Self::multiplicative_generator();
+        let mut c = Self::root_of_unity();
+
+        // div^(2^s - 1)
+        let mut div_pow = *div;
+        for _ in 1..s {
+            div_pow.square();
+            div_pow.mul_assign(div);
+        }
+
+        // y = num * div^(2^s - 1) * (num * div^(2^(s+1) - 1))^((t - 1)/2)
+        let mut tv = div_pow;
+        tv.square();
+        tv.mul_assign(div);
+        tv.mul_assign(num);
+        let mut y = tv.pow_vartime(Self::t_minus_1_over_2());
+        y.mul_assign(&div_pow);
+
+        // b = y^2 * div / num lies in the 2^s-th roots of unity, and is a square
+        // there iff num / div is a square.
+        let mut b = y;
+        b.mul_assign(div);
+        y.mul_assign(num);
+        b.mul_assign(&y);
+
+        let is_square = b.is_zero() || b.pow_2_pow(s - 1) == Self::one();
+        if !is_square {
+            // Switch to z * num / div, whose root of unity is b * c.
+            let mut z_root = z.pow_vartime(Self::t_minus_1_over_2());
+            z_root.mul_assign(&z);
+            y.mul_assign(&z_root);
+            b.mul_assign(&c);
+        }
+
+        // Tonelli-Shanks, clearing the remaining 2-power part of b.
+        for i in (2..=s).rev() {
+            let clear = b.pow_2_pow(i - 2) != Self::one();
+            let mut y_c = y;
+            y_c.mul_assign(&c);
+            c.square();
+            if clear {
+                y = y_c;
+                b.mul_assign(&c);
+            }
+        }
+
+        (is_square, y)
+    }
 }
 
 /// This trait represents a wrapper around a biginteger which can encode any element of a particular
@@ -94,6 +450,7 @@ pub trait PrimeFieldRepr: Sized +
                           'static +
                           rand::Rand +
                           AsRef<[u64]> +
+                          AsMut<[u64]> +
                           From<u64>
 {
     /// Subtract another reprensetation from this one, returning the borrow bit.
@@ -108,6 +465,15 @@ pub trait PrimeFieldRepr: Sized +
     /// Returns true iff this number is zero.
     fn is_zero(&self) -> bool;
 
+    /// Returns true iff this number is one. This is the integer one, which is
+    /// what `into_repr()` returns for `Field::one()`; the element's internal
+    /// Montgomery form is R instead.
+    fn is_one(&self) -> bool
+    {
+        let limbs = self.as_ref();
+        limbs[0] == 1 && limbs[1..].iter().all(|&limb| limb == 0)
+    }
+
     /// Returns true iff this number is odd.
     fn is_odd(&self) -> bool;
 
@@ -121,128 +487,1339 @@ pub trait PrimeFieldRepr: Sized +
     /// Performs a leftwise bitshift of this number, effectively multiplying
     /// it by 2. Overflow is ignored.
     fn mul2(&mut self);
-}
-
-/// This represents an element of a prime field.
-pub trait PrimeField: Field
-{
-    /// The prime field can be converted back and forth into this biginteger
-    /// representation.
-    type Repr: PrimeFieldRepr;
 
-    /// Convert this prime field element into a biginteger representation.
-    fn from_repr(Self::Repr) -> Result<Self, ()>;
+    /// Performs a rightwise bitshift of this number by `n` bits. Shifting by at
+    /// least the width of the representation zeroes it.
+    fn shr(&mut self, n: u32)
+    {
+        let limbs = self.as_mut();
+        let words = (n / 64) as usize;
+        let bits = n % 64;
 
-    /// Convert a biginteger reprensentation into a prime field element, if
-    /// the number is an element of the field.
-    fn into_repr(&self) -> Self::Repr;
+        // Limbs are read at or above the index being written, so this can be
+        // done in place from the bottom up.
+        for i in 0..limbs.len() {
+            let src = i + words;
+            let mut limb = 0;
+            if src < limbs.len() {
+                limb = limbs[src] >> bits;
+                if bits > 0 && src + 1 < limbs.len() {
+                    limb |= limbs[src + 1] << (64 - bits);
+                }
+            }
+            limbs[i] = limb;
+        }
+    }
 
-    /// Returns the field characteristic; the modulus.
-    fn char() -> Self::Repr;
+    /// Performs a leftwise bitshift of this number by `n` bits. Overflow is
+    /// ignored, so shifting by at least the width of the representation zeroes it.
+    fn shl(&mut self, n: u32)
+    {
+        let limbs = self.as_mut();
+        let words = (n / 64) as usize;
+        let bits = n % 64;
 
-    /// Returns how many bits are needed to represent an element of this
-    /// field.
-    fn num_bits() -> u32;
+        // Limbs are read at or below the index being written, so this can be
+        // done in place from the top down.
+        for i in (0..limbs.len()).rev() {
+            let mut limb = 0;
+            if i >= words {
+                limb = limbs[i - words] << bits;
+                if bits > 0 && i > words {
+                    limb |= limbs[i - words - 1] >> (64 - bits);
+                }
+            }
+            limbs[i] = limb;
+        }
+    }
 
-    /// Returns how many bits of information can be reliably stored in the
-    /// field element.
-    fn capacity() -> u32;
+    /// Rotates the bits of the whole representation left by `n` bits, wrapping
+    /// the bits shifted out at the top around to the bottom.
+    fn rotate_left(&mut self, n: u32)
+    {
+        let width = 64 * self.as_ref().len() as u32;
+        let n = n % width;
 
-    /// Returns the multiplicative generator of `char()` - 1 order. This element
-    /// must also be quadratic nonresidue.
-    fn multiplicative_generator() -> Self;
+        let mut high = *self;
+        high.shr(width - n);
+        self.shl(n);
+        for (a, b) in self.as_mut().iter_mut().zip(high.as_ref()) {
+            *a |= b;
+        }
+    }
 
-    /// Returns s such that 2^s * t = `char()` - 1 with t odd.
-    fn s() -> usize;
+    /// Rotates the bits of the whole representation right by `n` bits, wrapping
+    /// the bits shifted out at the bottom around to the top.
+    fn rotate_right(&mut self, n: u32)
+    {
+        let width = 64 * self.as_ref().len() as u32;
+        self.rotate_left(width - n % width);
+    }
 
-    /// Returns the 2^s root of unity computed by exponentiating the `multiplicative_generator()`
-    /// by t.
-    fn root_of_unity() -> Self;
-}
+    /// Splits this number at bit `k`, returning `(self mod 2^k, self >> k)`.
+    fn split_at_bit(&self, k: u32) -> (Self, Self)
+    {
+        let mut hi = *self;
+        hi.shr(k);
 
-pub struct BitIterator<E> {
-    t: E,
-    n: usize
-}
+        let mut lo = *self;
+        let words = (k / 64) as usize;
+        let bits = k % 64;
+        for (i, limb) in lo.as_mut().iter_mut().enumerate() {
+            if i == words {
+                *limb &= (1 << bits) - 1;
+            } else if i > words {
+                *limb = 0;
+            }
+        }
 
-impl<E: AsRef<[u64]>> BitIterator<E> {
-    fn new(t: E) -> Self {
-        let n = t.as_ref().len() * 64;
+        (lo, hi)
+    }
 
-        BitIterator {
-            t: t,
-            n: n
+    /// Constructs a number from a `u128`. With a single limb, the high 64 bits are
+    /// dropped.
+    fn from_u128(val: u128) -> Self
+    {
+        let mut repr = Self::from(val as u64);
+        if let Some(hi) = repr.as_mut().get_mut(1) {
+            *hi = (val >> 64) as u64;
         }
+
+        repr
     }
-}
 
-impl<E: AsRef<[u64]>> Iterator for BitIterator<E> {
-    type Item = bool;
+    /// Returns bit `i` of this number, or false if `i` is beyond the width of the
+    /// representation.
+    fn bit(&self, i: usize) -> bool
+    {
+        match self.as_ref().get(i / 64) {
+            Some(limb) => limb & (1 << (i % 64)) != 0,
+            None => false
+        }
+    }
 
-    fn next(&mut self) -> Option<bool> {
-        if self.n == 0 {
-            None
+    /// Sets bit `i` of this number to `value`. Panics if `i` is beyond the width
+    /// of the representation.
+    fn set_bit(&mut self, i: usize, value: bool)
+    {
+        let limb = &mut self.as_mut()[i / 64];
+        if value {
+            *limb |= 1 << (i % 64);
         } else {
-            self.n -= 1;
-            let part = self.n / 64;
-            let bit = self.n - (64 * part);
-
-            Some(self.t.as_ref()[part] & (1 << bit) > 0)
+            *limb &= !(1 << (i % 64));
         }
     }
-}
 
-#[test]
-fn test_bit_iterator() {
-    let mut a = BitIterator::new([0xa953d79b83f6ab59, 0x6dea2059e200bd39]);
-    let expected = "01101101111010100010000001011001111000100000000010111101001110011010100101010011110101111001101110000011111101101010101101011001";
+    /// Iterates over the bits of this number, from the most significant set bit
+    /// down to the least significant bit.
+    fn bits(&self) -> BitIterator<Self>
+    {
+        BitIterator {
+            t: *self,
+            n: self.num_bits() as usize
+        }
+    }
 
-    for e in expected.chars() {
-        assert!(a.next().unwrap() == (e == '1'));
+    /// Iterates over the bits of this number, from the least significant bit up
+    /// to the most significant set bit.
+    fn bits_le(&self) -> BitIteratorLe<Self>
+    {
+        BitIteratorLe {
+            t: *self,
+            i: 0,
+            n: self.num_bits() as usize
+        }
     }
 
-    assert!(a.next().is_none());
+    /// Sets this number to `(self + other) mod modulus`, given that both numbers are
+    /// less than `modulus`.
+    fn add_mod(&mut self, other: &Self, modulus: &Self)
+    {
+        // If the sum overflows the representation, subtracting the modulus wraps it
+        // back around.
+        let carry = self.add_nocarry(other);
+        if carry || *self >= *modulus {
+            self.sub_noborrow(modulus);
+        }
+    }
 
-    let expected = "1010010101111110101010000101101011101000011101110101001000011001100100100011011010001011011011010001011011101100110100111011010010110001000011110100110001100110011101101000101100011100100100100100001010011101010111110011101011000011101000111011011101011001";
+    /// Returns true iff this number is less than `modulus`.
+    fn is_reduced_against(&self, modulus: &Self) -> bool
+    {
+        self < modulus
+    }
 
-    let mut a = BitIterator::new([0x429d5f3ac3a3b759, 0xb10f4c66768b1c92, 0x92368b6d16ecd3b4, 0xa57ea85ae8775219]);
+    /// Returns a number with uniformly random limbs.
+    fn random<R: rand_core::RngCore + ?Sized>(rng: &mut R) -> Self
+    {
+        let mut repr = Self::from(0);
+        for limb in repr.as_mut() {
+            *limb = rng.next_u64();
+        }
 
-    for e in expected.chars() {
-        assert!(a.next().unwrap() == (e == '1'));
+        repr
     }
 
-    assert!(a.next().is_none());
-}
+    /// Computes the full product of this number and `other`, without reducing it,
+    /// returning its low and high halves.
+    fn mul_wide(&self, other: &Self) -> (Self, Self)
+    {
+        let (a, b) = (self.as_ref(), other.as_ref());
+        let n = a.len();
 
-/// Calculate a - b - borrow, returning the result and modifying
-/// the borrow value.
-#[inline(always)]
-pub fn sbb(a: u64, b: u64, borrow: &mut u64) -> u64 {
-    let tmp = (1u128 << 64) + (a as u128) - (b as u128) - (*borrow as u128);
+        let mut prod = vec![0u64; 2 * n];
+        for (i, ai) in a.iter().enumerate() {
+            let mut carry = 0;
+            for (j, bj) in b.iter().enumerate() {
+                prod[i + j] = mac_with_carry(prod[i + j], *ai, *bj, &mut carry);
+            }
+            prod[i + n] = carry;
+        }
 
-    *borrow = if tmp >> 64 == 0 { 1 } else { 0 };
+        let mut lo = Self::from(0);
+        let mut hi = Self::from(0);
+        lo.as_mut().copy_from_slice(&prod[..n]);
+        hi.as_mut().copy_from_slice(&prod[n..]);
 
-    tmp as u64
-}
+        (lo, hi)
+    }
 
-/// Calculate a + b + carry, returning the sum and modifying the
-/// carry value.
-#[inline(always)]
-pub fn adc(a: u64, b: u64, carry: &mut u64) -> u64 {
-    let tmp = (a as u128) + (b as u128) + (*carry as u128);
+    /// Computes `self += a * c`, returning the limb that overflowed the top of
+    /// this number.
+    fn add_scaled(&mut self, a: &Self, c: u64) -> u64
+    {
+        let mut carry = 0;
+        for (s, a) in self.as_mut().iter_mut().zip(a.as_ref()) {
+            *s = mac_with_carry(*s, *a, c, &mut carry);
+        }
 
-    *carry = (tmp >> 64) as u64;
+        carry
+    }
 
-    tmp as u64
-}
+    /// Returns the number of bits in which this number differs from `other`.
+    fn hamming_distance(&self, other: &Self) -> u32
+    {
+        self.as_ref().iter().zip(other.as_ref()).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
 
-/// Calculate a + (b * c) + carry, returning the least significant digit
+    /// Returns the low 128 bits of this number.
+    fn low_u128(&self) -> u128
+    {
+        let limbs = self.as_ref();
+        let hi = limbs.get(1).cloned().unwrap_or(0);
+
+        ((hi as u128) << 64) | (limbs[0] as u128)
+    }
+
+    /// Writes this number as big-endian bytes, most significant limb first.
+    fn write_be<W: Write>(&self, mut writer: W) -> io::Result<()>
+    {
+        for digit in self.as_ref().iter().rev() {
+            writer.write_all(&digit.to_be_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a number written by `write_be` into this one.
+    fn read_be<R: Read>(&mut self, mut reader: R) -> io::Result<()>
+    {
+        for digit in self.as_mut().iter_mut().rev() {
+            let mut buf = [0u8; 8];
+            reader.read_exact(&mut buf)?;
+            *digit = u64::from_be_bytes(buf);
+        }
+
+        Ok(())
+    }
+
+    /// Writes this number as little-endian bytes, least significant limb first.
+    fn write_le<W: Write>(&self, mut writer: W) -> io::Result<()>
+    {
+        for digit in self.as_ref().iter() {
+            writer.write_all(&digit.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a number written by `write_le` into this one.
+    fn read_le<R: Read>(&mut self, mut reader: R) -> io::Result<()>
+    {
+        for digit in self.as_mut().iter_mut() {
+            let mut buf = [0u8; 8];
+            reader.read_exact(&mut buf)?;
+            *digit = u64::from_le_bytes(buf);
+        }
+
+        Ok(())
+    }
+}
+
+/// This represents an element of a prime field.
+pub trait PrimeField: Field
+{
+    /// The prime field can be converted back and forth into this biginteger
+    /// representation.
+    type Repr: PrimeFieldRepr;
+
+    /// The modulus, as returned by `char()`, for use in constant contexts.
+    const MODULUS: Self::Repr;
+
+    /// The number of bits needed to represent an element, as returned by
+    /// `num_bits()`, for use in constant contexts.
+    const NUM_BITS: u32;
+
+    /// The number of bits of information that can be reliably stored in an
+    /// element, as returned by `capacity()`, for use in constant contexts.
+    const CAPACITY: u32;
+
+    /// Convert this prime field element into a biginteger representation.
+    fn from_repr(Self::Repr) -> Result<Self, PrimeFieldDecodingError>;
+
+    /// Convert a biginteger reprensentation into a prime field element, if
+    /// the number is an element of the field.
+    ///
+    /// Derived fields compute this without branching on the value of the element.
+    fn into_repr(&self) -> Self::Repr;
+
+    /// Constructs the element `val`, reduced modulo `char()`.
+    fn from_u64(val: u64) -> Self;
+
+    /// Constructs the element `hi * 2^(64 * limbs) + lo`, reduced modulo `char()`,
+    /// where `limbs` is the number of limbs in the representation. This reduces a
+    /// double-width product of two representations.
+    fn reduce_double_width(lo: Self::Repr, hi: Self::Repr) -> Self;
+
+    /// An alias for `into_repr()`, for callers that want to spell out that the
+    /// conversion must not branch on the value. It behaves identically: the
+    /// constant-time guarantee comes from `into_repr()` itself, which derived
+    /// fields implement without branching.
+    #[cfg(feature = "subtle")]
+    fn to_repr_ct(&self) -> Self::Repr
+    {
+        self.into_repr()
+    }
+
+    /// Writes the biginteger representation of this element into `buf`, as
+    /// `into_repr()` would return it.
+    fn write_repr_into(&self, buf: &mut Self::Repr);
+
+    /// Converts each element into its biginteger representation.
+    fn batch_into_repr(elements: &[Self]) -> Vec<Self::Repr>
+    {
+        let mut reprs = vec![Self::Repr::from(0); elements.len()];
+        Self::batch_write_repr_into(elements, &mut reprs);
+        reprs
+    }
+
+    /// Writes the biginteger representation of each element into the
+    /// corresponding entry of `buf`. Panics if the slices differ in length.
+    fn batch_write_repr_into(elements: &[Self], buf: &mut [Self::Repr])
+    {
+        assert_eq!(elements.len(), buf.len());
+
+        for (a, repr) in elements.iter().zip(buf.iter_mut()) {
+            a.write_repr_into(repr);
+        }
+    }
+
+    /// Encodes this element as big-endian bytes spanning the whole representation,
+    /// storing `flag` in the most significant bit. Fails if the modulus leaves no
+    /// unused bits at the top of the representation, which derived fields always
+    /// do.
+    fn to_bytes_with_flag(&self, flag: bool) -> Result<Vec<u8>, PrimeFieldDecodingError>
+    {
+        let repr = self.into_repr();
+        let limbs = repr.as_ref();
+        if Self::num_bits() >= 64 * limbs.len() as u32 {
+            return Err(PrimeFieldDecodingError::NoFreeBits);
+        }
+
+        let mut bytes = Vec::with_capacity(limbs.len() * 8);
+        for limb in limbs.iter().rev() {
+            for i in (0..8).rev() {
+                bytes.push((limb >> (i * 8)) as u8);
+            }
+        }
+
+        if flag {
+            bytes[0] |= 0x80;
+        }
+
+        Ok(bytes)
+    }
+
+    /// Decodes an element and flag encoded by `to_bytes_with_flag`. Fails if the
+    /// length is wrong, if the remaining value is not canonical, or if the modulus
+    /// leaves no unused bits at the top of the representation.
+    fn from_bytes_with_flag(bytes: &[u8]) -> Result<(Self, bool), PrimeFieldDecodingError>
+    {
+        let limbs = Self::char().as_ref().len();
+        if Self::num_bits() >= 64 * limbs as u32 {
+            return Err(PrimeFieldDecodingError::NoFreeBits);
+        }
+        if bytes.len() != limbs * 8 {
+            return Err(PrimeFieldDecodingError::InvalidLength { expected: limbs * 8, actual: bytes.len() });
+        }
+
+        let flag = bytes[0] & 0x80 != 0;
+
+        let repr = repr_from_be_bytes(Some(bytes[0] & 0x7f).into_iter().chain(bytes[1..].iter().cloned()));
+
+        Self::from_repr(repr).map(|a| (a, flag))
+    }
+
+    /// Decodes an element from bytes spanning the whole representation, in the
+    /// given byte order. Fails if the length is wrong or the value is not canonical.
+    fn from_bytes(bytes: &[u8], order: ByteOrder) -> Result<Self, PrimeFieldDecodingError>
+    {
+        let expected = Self::repr_byte_len();
+        if bytes.len() != expected {
+            return Err(PrimeFieldDecodingError::InvalidLength { expected, actual: bytes.len() });
+        }
+
+        let repr = match order {
+            ByteOrder::BigEndian => repr_from_be_bytes(bytes.iter().cloned()),
+            ByteOrder::LittleEndian => repr_from_be_bytes(bytes.iter().rev().cloned())
+        };
+
+        Self::from_repr(repr)
+    }
+
+    /// Returns the number of bytes in an encoding spanning the whole representation.
+    fn repr_byte_len() -> usize
+    {
+        Self::char().as_ref().len() * 8
+    }
+
+    /// Decodes an element from exactly `repr_byte_len()` big-endian bytes. Fails if
+    /// the length is wrong or the value is not canonical.
+    fn from_bytes_be_exact(bytes: &[u8]) -> Result<Self, PrimeFieldDecodingError>
+    {
+        Self::from_bytes(bytes, ByteOrder::BigEndian)
+    }
+
+    /// Decodes an element from at most `repr_byte_len()` big-endian bytes, treating
+    /// shorter inputs as if they were padded with leading zeros. Fails if the input
+    /// is too long or the value is not canonical.
+    fn from_bytes_be_padded(bytes: &[u8]) -> Result<Self, PrimeFieldDecodingError>
+    {
+        let expected = Self::repr_byte_len();
+        if bytes.len() > expected {
+            return Err(PrimeFieldDecodingError::InvalidLength { expected, actual: bytes.len() });
+        }
+
+        let mut padded = vec![0u8; expected - bytes.len()];
+        padded.extend_from_slice(bytes);
+
+        Self::from_bytes(&padded, ByteOrder::BigEndian)
+    }
+
+    /// Returns the number of bytes in the canonical encoding, which is just wide
+    /// enough to hold the modulus.
+    fn canonical_byte_len() -> usize
+    {
+        (Self::num_bits() as usize).div_ceil(8)
+    }
+
+    /// Encodes this element as exactly `canonical_byte_len()` big-endian bytes.
+    fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut bytes = vec![];
+        self.into_repr().write_be(&mut bytes).unwrap();
+
+        let skip = bytes.len() - Self::canonical_byte_len();
+        bytes.split_off(skip)
+    }
+
+    /// Decodes an element encoded by `to_bytes`. Fails if the length is not exactly
+    /// `canonical_byte_len()` or the value is not canonical.
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, PrimeFieldDecodingError>
+    {
+        let expected = Self::canonical_byte_len();
+        if bytes.len() != expected {
+            return Err(PrimeFieldDecodingError::InvalidLength { expected, actual: bytes.len() });
+        }
+
+        Self::from_bytes_be_padded(bytes)
+    }
+
+    /// Parses an element from a string of decimal digits, reducing the value modulo
+    /// `char()`. Fails if the string is empty or contains anything but digits.
+    #[allow(clippy::result_unit_err)]
+    fn from_str(s: &str) -> Result<Self, ()>
+    {
+        if s.is_empty() {
+            return Err(());
+        }
+
+        let ten = Self::from_repr(Self::Repr::from(10)).unwrap();
+
+        let mut res = Self::zero();
+        for c in s.chars() {
+            let digit = match c.to_digit(10) {
+                Some(d) => d,
+                None => return Err(())
+            };
+
+            res.mul_assign(&ten);
+            res.add_assign(&Self::from_repr(Self::Repr::from(digit as u64)).unwrap());
+        }
+
+        Ok(res)
+    }
+
+    /// Parses an element from a string of decimal digits. Unlike `from_str`, this
+    /// fails rather than reducing values that are not less than `char()`.
+    fn from_str_checked(s: &str) -> Result<Self, FromStrError>
+    {
+        if s.is_empty() {
+            return Err(FromStrError::Empty);
+        }
+        if !s.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(FromStrError::InvalidChar);
+        }
+
+        let modulus = Self::char();
+        let mut repr = Self::Repr::from(0);
+        for c in s.bytes() {
+            // Compute repr * 10 + digit, which is out of range if it overflows.
+            let mut carry = (c - b'0') as u64;
+            for limb in repr.as_mut() {
+                *limb = mac_with_carry(0, *limb, 10, &mut carry);
+            }
+
+            if carry != 0 || repr >= modulus {
+                return Err(FromStrError::OutOfRange);
+            }
+        }
+
+        Ok(Self::from_repr(repr).expect("value is less than the modulus"))
+    }
+
+    /// Feeds the modulus into `hasher` as the `canonical_byte_len()` big-endian
+    /// bytes that `modulus_bytes()` returns, without allocating.
+    #[cfg(feature = "digest")]
+    fn hash_char_into<H: digest::Update>(hasher: &mut H)
+    {
+        update_canonical_be::<Self, H>(hasher, &Self::char());
+    }
+
+    /// Feeds this element into `hasher` as the `canonical_byte_len()` big-endian
+    /// bytes that `to_bytes()` returns, without allocating.
+    #[cfg(feature = "digest")]
+    fn hash_into<H: digest::Update>(&self, hasher: &mut H)
+    {
+        update_canonical_be::<Self, H>(hasher, &self.into_repr());
+    }
+
+    /// Returns the coefficient applied by the Frobenius map of the given power to
+    /// the generators of extensions. A prime field's Frobenius map is the identity,
+    /// so this is always one; it lets extension towers treat their base fields
+    /// uniformly.
+    fn frobenius_coeff(_power: usize) -> Self
+    {
+        Self::one()
+    }
+
+    /// Returns the field characteristic; the modulus. Like `into_repr()`, this is
+    /// the plain integer, not its Montgomery form.
+    fn char() -> Self::Repr;
+
+    /// Returns the residues of this element's canonical value modulo each of
+    /// `moduli`. Panics if any modulus is zero.
+    fn to_rns(&self, moduli: &[u64]) -> Vec<u64>
+    {
+        let repr = self.into_repr();
+
+        moduli.iter().map(|&m| {
+            repr.as_ref().iter().rev().fold(0u128, |rem, &limb| {
+                ((rem << 64) | (limb as u128)) % (m as u128)
+            }) as u64
+        }).collect()
+    }
+
+    /// Reconstructs the element from its residues modulo each of `moduli`, which
+    /// inverts `to_rns` when the product of the moduli exceeds `char()`. Panics if
+    /// the slices differ in length or the moduli are not pairwise coprime.
+    fn from_rns(residues: &[u64], moduli: &[u64]) -> Self
+    {
+        assert_eq!(residues.len(), moduli.len());
+
+        // Garner's algorithm finds the mixed-radix digits v_i < m_i of the unique
+        // x < prod m_i with the given residues, where
+        // x = v_0 + m_0 * (v_1 + m_1 * (v_2 + ...)).
+        let mut digits: Vec<u64> = Vec::with_capacity(moduli.len());
+        for (&r, &m) in residues.iter().zip(moduli) {
+            let m = m as u128;
+
+            // The value of the digits so far modulo m, and m_0 * ... * m_{i-1} mod m.
+            let mut partial = 0u128;
+            let mut radix = 1u128;
+            for (&v, &mj) in digits.iter().zip(moduli) {
+                partial = (partial + (v as u128) * radix) % m;
+                radix = radix * ((mj as u128) % m) % m;
+            }
+
+            let inv = inverse_mod_u64(radix as u64, m as u64).expect("moduli must be pairwise coprime");
+            let diff = ((r as u128) % m + m - partial) % m;
+            digits.push((diff * (inv as u128) % m) as u64);
+        }
+
+        let mut res = Self::zero();
+        for (&v, &m) in digits.iter().zip(moduli).rev() {
+            res.mul_assign(&Self::from_u64(m));
+            res.add_assign(&Self::from_u64(v));
+        }
+
+        res
+    }
+
+    /// Returns the modulus as `canonical_byte_len()` big-endian bytes, the same
+    /// encoding that `to_bytes` uses for elements.
+    fn modulus_bytes() -> Vec<u8>
+    {
+        let mut bytes = vec![];
+        Self::char().write_be(&mut bytes).unwrap();
+
+        let skip = bytes.len() - Self::canonical_byte_len();
+        bytes.split_off(skip)
+    }
+
+    /// Compares two representations as integers.
+    fn repr_value_cmp(a: &Self::Repr, b: &Self::Repr) -> Ordering
+    {
+        a.cmp(b)
+    }
+
+    /// Returns how many bits are needed to represent an element of this
+    /// field.
+    fn num_bits() -> u32;
+
+    /// Returns true iff the field characteristic fits in a `u64`.
+    fn characteristic_fits_in_u64() -> bool
+    {
+        Self::num_bits() <= 64
+    }
+
+    /// Returns how many bits of information can be reliably stored in the
+    /// field element. This is the largest `k` such that `2^k <= char()`, so
+    /// every `k`-bit integer is a valid element. Since `char()` has
+    /// `num_bits()` bits, this is always `num_bits() - 1`, and no tighter
+    /// bound exists for any modulus.
+    fn capacity() -> u32;
+
+    /// Returns the multiplicative generator of `char()` - 1 order. This element
+    /// must also be quadratic nonresidue.
+    fn multiplicative_generator() -> Self;
+
+    /// Multiplies this element by the quadratic nonresidue used to construct
+    /// extension fields, which is the `multiplicative_generator()`.
+    fn mul_by_nonresidue(&self) -> Self
+    {
+        let mut res = *self;
+        res.mul_assign(&Self::multiplicative_generator());
+        res
+    }
+
+    /// Returns s such that 2^s * t = `char()` - 1 with t odd.
+    fn s() -> usize;
+
+    /// Returns the 2^s root of unity computed by exponentiating the `multiplicative_generator()`
+    /// by t.
+    fn root_of_unity() -> Self;
+
+    /// Returns t such that 2^s * t = `char()` - 1 with t odd.
+    fn t() -> Self::Repr;
+
+    /// Returns (t - 1)/2, where t is given by `t()`.
+    fn t_minus_1_over_2() -> Self::Repr;
+
+    /// Returns (`char()` - 1)/2.
+    fn modulus_minus_one_over_two() -> Self::Repr;
+
+    /// Returns the unique `n`th root of this element if `n` is coprime to `char()` - 1,
+    /// and `None` otherwise.
+    fn nth_root(&self, n: u64) -> Option<Self>
+    {
+        if n == 0 {
+            return None;
+        }
+
+        if n == 1 {
+            return Some(*self);
+        }
+
+        // The modulus is odd, so this cannot borrow.
+        let mut p_minus_1 = Self::char().as_ref().to_vec();
+        p_minus_1[0] -= 1;
+
+        // When gcd(n, p - 1) = 1, x -> x^n is a permutation of the field whose inverse
+        // is x -> x^e with e = n^-1 mod p - 1. We find k such that n divides
+        // k(p - 1) + 1, and then e = (k(p - 1) + 1) / n.
+        let r = p_minus_1.iter().rev().fold(0u128, |acc, &limb| {
+            ((acc << 64) | (limb as u128)) % (n as u128)
+        }) as u64;
+        let k = match inverse_mod_u64(r, n) {
+            Some(inv) => n - inv,
+            None => return None
+        };
+
+        let mut e = Vec::with_capacity(p_minus_1.len() + 1);
+        let mut carry = 0;
+        for limb in p_minus_1 {
+            e.push(mac_with_carry(0, limb, k, &mut carry));
+        }
+        e.push(carry);
+
+        let mut carry = 1;
+        for limb in e.iter_mut() {
+            *limb = adc(*limb, 0, &mut carry);
+        }
+
+        let mut rem = 0u128;
+        for limb in e.iter_mut().rev() {
+            let cur = (rem << 64) | (*limb as u128);
+            *limb = (cur / (n as u128)) as u64;
+            rem = cur % (n as u128);
+        }
+        debug_assert!(rem == 0);
+
+        Some(self.pow_vartime(e))
+    }
+
+    /// Samples a uniformly random quadratic nonresidue, for use in tests.
+    #[cfg(feature = "test-util")]
+    fn random_nonresidue<R: rand_core::RngCore + ?Sized>(rng: &mut R) -> Self
+    {
+        // By Euler's criterion, a is a nonresidue iff a^((p - 1)/2) != 1 for
+        // nonzero a.
+        let exp = Self::modulus_minus_one_over_two();
+
+        loop {
+            let a = Self::random_nonzero(rng);
+            if a.pow_vartime(exp) != Self::one() {
+                return a;
+            }
+        }
+    }
+
+    /// Generates `count` random known-answer triples `(base, exp, base^exp)` for
+    /// validating exponentiation against other implementations, for use in tests.
+    #[cfg(feature = "test-util")]
+    fn exponentiation_test_vectors<R: rand_core::RngCore + ?Sized>(rng: &mut R, count: usize) -> Vec<(Self, u64, Self)>
+    {
+        (0..count).map(|_| {
+            let base = Self::random(rng);
+            let exp = rng.next_u64();
+
+            (base, exp, base.pow_vartime([exp]))
+        }).collect()
+    }
+
+    /// Samples `samples` random elements and returns Pearson's chi-squared statistic
+    /// for the distribution of their top 8 bits, divided by its degrees of freedom.
+    /// This is close to 1 for an unbiased sampler, for use in tests.
+    #[cfg(feature = "test-util")]
+    fn sampling_bias_estimate<R: rand_core::RngCore + ?Sized>(rng: &mut R, samples: usize) -> f64
+    {
+        let to_f64 = |r: &Self::Repr| {
+            r.as_ref().iter().rev().fold(0f64, |acc, &limb| acc * 18446744073709551616.0 + limb as f64)
+        };
+
+        // Elements are bucketed by their top 8 bits. Every bucket below the modulus
+        // is full, except for the last, which is only partially covered.
+        let shift = Self::num_bits().saturating_sub(8);
+        let (lo, hi) = Self::char().split_at_bit(shift);
+        let full = hi.as_ref()[0] as usize;
+        let partial = to_f64(&lo) / 2f64.powi(shift as i32);
+        let total = full as f64 + partial;
+
+        let mut counts = vec![0usize; full + 1];
+        for _ in 0..samples {
+            let (_, top) = Self::random(rng).into_repr().split_at_bit(shift);
+            counts[top.as_ref()[0] as usize] += 1;
+        }
+
+        let mut chi2 = 0.0;
+        for (bucket, &count) in counts.iter().enumerate() {
+            let weight = if bucket < full { 1.0 } else { partial };
+            let expected = weight / total * samples as f64;
+            if expected > 0.0 {
+                chi2 += (count as f64 - expected).powi(2) / expected;
+            } else if count > 0 {
+                return f64::INFINITY;
+            }
+        }
+
+        chi2 / full as f64
+    }
+
+    /// Samples a random primitive `2^log_order`-th root of unity, for use in
+    /// tests of NTTs. Returns `None` if `log_order` exceeds `s()`, as no such
+    /// root exists in the field.
+    #[cfg(feature = "test-util")]
+    fn random_primitive_root_of_unity<R: rand_core::RngCore + ?Sized>(rng: &mut R, log_order: u32) -> Option<Self>
+    {
+        if log_order as usize > Self::s() {
+            return None;
+        }
+
+        // Raising a nonzero element to (p - 1)/2^log_order always gives a root
+        // of unity of that order; it is primitive iff squaring it
+        // log_order - 1 times does not already reach one.
+        let mut exp = Self::char();
+        exp.sub_noborrow(&Self::Repr::from(1));
+        exp.shr(log_order);
+
+        loop {
+            let root = Self::random_nonzero(rng).pow_vartime(exp);
+            if log_order == 0 || root.pow_2_pow(log_order - 1) != Self::one() {
+                return Some(root);
+            }
+        }
+    }
+
+    /// Returns the modulus of the field as a `BigUint`.
+    #[cfg(feature = "bigint")]
+    fn modulus_biguint() -> num_bigint::BigUint
+    {
+        let mut bytes = vec![];
+        Self::char().write_le(&mut bytes).unwrap();
+
+        num_bigint::BigUint::from_bytes_le(&bytes)
+    }
+
+    /// Computes the square root of each element, if it is a square. There is no
+    /// work to share between elements, so this is no faster than calling `sqrt`
+    /// on each of them.
+    fn batch_sqrt(elements: &[Self]) -> Vec<Option<Self>>
+    where Self: SqrtField
+    {
+        elements.iter().map(|a| a.sqrt()).collect()
+    }
+
+    /// Returns the square root of this element whose canonical representation is
+    /// even, if this element is a square.
+    fn sqrt_canonical(&self) -> Option<Self>
+    where Self: SqrtField
+    {
+        self.sqrt().map(|mut root| {
+            let odd = root.into_repr().is_odd();
+            root.conditional_negate(odd);
+            root
+        })
+    }
+
+    /// Computes the Legendre symbol of this element, by Euler's criterion.
+    fn legendre(&self) -> LegendreSymbol
+    {
+        // The running time of this depends only on the exponent, which is public.
+        let s = self.pow_vartime(Self::modulus_minus_one_over_two());
+
+        if s.is_zero() {
+            LegendreSymbol::Zero
+        } else if s == Self::one() {
+            LegendreSymbol::QuadraticResidue
+        } else {
+            LegendreSymbol::QuadraticNonResidue
+        }
+    }
+
+    /// Computes the Legendre symbol of each of the elements. Unlike inversion,
+    /// there is no work to share between elements, so this is no faster than
+    /// calling `legendre` on each of them.
+    fn batch_legendre(elements: &[Self]) -> Vec<LegendreSymbol>
+    {
+        elements.iter().map(Self::legendre).collect()
+    }
+}
+
+/// The Legendre symbol of a prime field element: whether it is zero, a nonzero
+/// square, or not a square.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LegendreSymbol {
+    Zero = 0,
+    QuadraticResidue = 1,
+    QuadraticNonResidue = -1
+}
+
+/// The order of the bytes in an encoding of a field element.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian
+}
+
+/// An error that may occur when decoding a field element from bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrimeFieldDecodingError {
+    /// The encoding does not span the whole representation.
+    InvalidLength { expected: usize, actual: usize },
+    /// The encoded value is not less than the modulus.
+    NotInField,
+    /// The modulus leaves no unused bit at the top of the representation in
+    /// which to store a flag.
+    NoFreeBits
+}
+
+impl fmt::Display for PrimeFieldDecodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PrimeFieldDecodingError::InvalidLength { expected, actual } => {
+                write!(f, "encoding should be {} bytes, but is {} bytes", expected, actual)
+            },
+            PrimeFieldDecodingError::NotInField => {
+                write!(f, "value is not less than the field modulus")
+            },
+            PrimeFieldDecodingError::NoFreeBits => {
+                write!(f, "the representation has no free bits for a flag")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PrimeFieldDecodingError { }
+
+/// An error that may occur when parsing a field element from a decimal string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FromStrError {
+    /// The string is empty.
+    Empty,
+    /// The string contains something other than decimal digits.
+    InvalidChar,
+    /// The parsed value is not less than the modulus.
+    OutOfRange
+}
+
+impl fmt::Display for FromStrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FromStrError::Empty => write!(f, "string is empty"),
+            FromStrError::InvalidChar => write!(f, "string contains a character that is not a decimal digit"),
+            FromStrError::OutOfRange => write!(f, "value is not less than the field modulus")
+        }
+    }
+}
+
+impl std::error::Error for FromStrError { }
+
+/// Adds two polynomials given by their coefficients, constant term first. The
+/// shorter polynomial is padded with zero coefficients.
+pub fn add_poly<F: Field>(a: &[F], b: &[F]) -> Vec<F> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+
+    let mut res = long.to_vec();
+    for (r, s) in res.iter_mut().zip(short.iter()) {
+        r.add_assign(s);
+    }
+
+    res
+}
+
+/// Evaluates the polynomial with the given coefficients, constant term first, at
+/// `x` using Horner's rule.
+pub fn horner_eval<F: Field>(coeffs: &[F], x: &F) -> F {
+    let mut res = F::zero();
+
+    for c in coeffs.iter().rev() {
+        res.mul_assign(x);
+        res.add_assign(c);
+    }
+
+    res
+}
+
+/// Evaluates at `target` the polynomial of least degree passing through each
+/// `(points[i], values[i])`, using the barycentric formula with a single batch
+/// inversion. Panics if the slices differ in length or the points are not distinct.
+pub fn lagrange_interpolate_at<F: Field>(points: &[F], values: &[F], target: &F) -> F {
+    assert_eq!(points.len(), values.len());
+
+    let n = points.len();
+
+    // The barycentric weights are the inverses of prod_{k != j} (x_j - x_k).
+    let mut inverses = Vec::with_capacity(2 * n);
+    for (j, xj) in points.iter().enumerate() {
+        let mut denom = F::one();
+        for (k, xk) in points.iter().enumerate() {
+            if k != j {
+                let mut tmp = *xj;
+                tmp.sub_assign(xk);
+                denom.mul_assign(&tmp);
+            }
+        }
+        assert!(!denom.is_zero(), "interpolation points must be distinct");
+        inverses.push(denom);
+    }
+
+    // The formula divides by zero at the points themselves.
+    if let Some(j) = points.iter().position(|x| x == target) {
+        return values[j];
+    }
+
+    let mut l = F::one();
+    for xj in points {
+        let mut tmp = *target;
+        tmp.sub_assign(xj);
+        l.mul_assign(&tmp);
+        inverses.push(tmp);
+    }
+
+    batch_inversion(&mut inverses);
+
+    // l(target) * sum_j w_j * y_j / (target - x_j)
+    let mut res = F::zero();
+    for (j, y) in values.iter().enumerate() {
+        let mut tmp = *y;
+        tmp.mul_assign(&inverses[j]);
+        tmp.mul_assign(&inverses[n + j]);
+        res.add_assign(&tmp);
+    }
+    res.mul_assign(&l);
+
+    res
+}
+
+/// Inverts each of the nonzero elements in place using Montgomery's trick, which
+/// costs a single inversion. Zero elements are left untouched.
+pub fn batch_inversion<F: Field>(elements: &mut [F]) {
+    // Compute the running products of the nonzero elements.
+    let mut prod = Vec::with_capacity(elements.len());
+    let mut acc = F::one();
+    for e in elements.iter().filter(|e| !e.is_zero()) {
+        acc.mul_assign(e);
+        prod.push(acc);
+    }
+
+    // This cannot fail, as acc is a product of nonzero elements.
+    acc = acc.inverse().unwrap();
+
+    // Unwind the running products, stripping one element at a time.
+    for (e, p) in elements.iter_mut().rev()
+                          .filter(|e| !e.is_zero())
+                          .zip(prod.into_iter().rev().skip(1).chain(Some(F::one())))
+    {
+        let mut tmp = acc;
+        tmp.mul_assign(&p);
+        acc.mul_assign(e);
+        *e = tmp;
+    }
+}
+
+/// Returns the index of the first position at which the slices differ, or the
+/// length of the shorter slice if one is a prefix of the other, for use in tests.
+#[cfg(feature = "test-util")]
+pub fn first_difference<F: Field>(a: &[F], b: &[F]) -> Option<usize> {
+    match a.iter().zip(b).position(|(a, b)| a != b) {
+        Some(i) => Some(i),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None
+    }
+}
+
+/// Powers of a fixed base, precomputed for exponents of up to `exp_bits` bits in
+/// windows of `window` bits. Exponentiating with the table takes one multiplication
+/// per window and no squarings.
+pub struct PowTable<F: Field> {
+    window: usize,
+    // table[i][d] is base^(d * 2^(window * i))
+    table: Vec<Vec<F>>
+}
+
+impl<F: Field> PowTable<F> {
+    /// Precomputes the table for `base`. Panics if `window` is not between 1 and 16.
+    pub fn new(base: &F, window: usize, exp_bits: usize) -> Self {
+        assert!((1..=16).contains(&window), "window must be between 1 and 16 bits");
+
+        let windows = exp_bits.div_ceil(window);
+        let mut table = Vec::with_capacity(windows);
+
+        let mut cur = *base;
+        for _ in 0..windows {
+            let mut row = Vec::with_capacity(1 << window);
+            let mut acc = F::one();
+            for _ in 0..(1 << window) {
+                row.push(acc);
+                acc.mul_assign(&cur);
+            }
+            table.push(row);
+
+            // This is now cur^(2^window).
+            cur = acc;
+        }
+
+        PowTable { window, table }
+    }
+
+    /// Exponentiates the base by a number represented with `u64` limbs, least
+    /// significant digit first. Its running time depends on the exponent, so it
+    /// must only be used with public exponents. Panics if the exponent has more
+    /// bits than the table was built for.
+    pub fn pow_using_table<S: AsRef<[u64]>>(&self, exp: S) -> F {
+        let exp = exp.as_ref();
+        let bit = |i: usize| exp.get(i / 64).is_some_and(|limb| (limb >> (i % 64)) & 1 == 1);
+
+        let mut res = F::one();
+        for (i, row) in self.table.iter().enumerate() {
+            let mut digit = 0;
+            for j in (0..self.window).rev() {
+                digit = (digit << 1) | (bit(i * self.window + j) as usize);
+            }
+
+            if digit != 0 {
+                res.mul_assign(&row[digit]);
+            }
+        }
+
+        assert!(
+            (self.table.len() * self.window..exp.len() * 64).all(|i| !bit(i)),
+            "exponent is too large for the table"
+        );
+
+        res
+    }
+}
+
+/// Iterates over the bits of a number, most significant first.
+pub struct BitIterator<E> {
+    t: E,
+    n: usize
+}
+
+impl<E: AsRef<[u64]>> BitIterator<E> {
+    fn new(t: E) -> Self {
+        let n = t.as_ref().len() * 64;
+
+        BitIterator {
+            t: t,
+            n: n
+        }
+    }
+}
+
+impl<E: AsRef<[u64]>> Iterator for BitIterator<E> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.n == 0 {
+            None
+        } else {
+            self.n -= 1;
+            let part = self.n / 64;
+            let bit = self.n - (64 * part);
+
+            Some(self.t.as_ref()[part] & (1 << bit) > 0)
+        }
+    }
+}
+
+/// Iterates over the bits of a number, least significant first.
+pub struct BitIteratorLe<E> {
+    t: E,
+    i: usize,
+    n: usize
+}
+
+impl<E: AsRef<[u64]>> Iterator for BitIteratorLe<E> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.i == self.n {
+            None
+        } else {
+            let part = self.i / 64;
+            let bit = self.i - (64 * part);
+            self.i += 1;
+
+            Some(self.t.as_ref()[part] & (1 << bit) > 0)
+        }
+    }
+}
+
+#[test]
+fn test_bit_iterator() {
+    let mut a = BitIterator::new([0xa953d79b83f6ab59, 0x6dea2059e200bd39]);
+    let expected = "01101101111010100010000001011001111000100000000010111101001110011010100101010011110101111001101110000011111101101010101101011001";
+
+    for e in expected.chars() {
+        assert!(a.next().unwrap() == (e == '1'));
+    }
+
+    assert!(a.next().is_none());
+
+    let expected = "1010010101111110101010000101101011101000011101110101001000011001100100100011011010001011011011010001011011101100110100111011010010110001000011110100110001100110011101101000101100011100100100100100001010011101010111110011101011000011101000111011011101011001";
+
+    let mut a = BitIterator::new([0x429d5f3ac3a3b759, 0xb10f4c66768b1c92, 0x92368b6d16ecd3b4, 0xa57ea85ae8775219]);
+
+    for e in expected.chars() {
+        assert!(a.next().unwrap() == (e == '1'));
+    }
+
+    assert!(a.next().is_none());
+}
+
+#[test]
+fn test_bit_iterator_le() {
+    let a = [0xa953d79b83f6ab59, 0x6dea2059e200bd39];
+    let mut expected: Vec<bool> = BitIterator::new(a).collect();
+    expected.reverse();
+
+    assert_eq!(BitIteratorLe { t: a, i: 0, n: 128 }.collect::<Vec<_>>(), expected);
+
+    let mut a = BitIteratorLe { t: [0b1011], i: 0, n: 64 };
+    assert_eq!(a.by_ref().take(5).collect::<Vec<_>>(), [true, true, false, true, false]);
+    assert_eq!(a.count(), 59);
+}
+
+/// Constructs a field element from a small integer in a `const` context, for
+/// fields implemented with `#[derive(PrimeField)]`, e.g.
+/// `const SEVEN: Fr = field_const!(Fr, 7);`
+#[macro_export]
+macro_rules! field_const {
+    ($field:ty, $val:expr) => {
+        <$field>::from_u64_const($val)
+    };
+}
+
+/// Builds a representation from bytes, most significant first. Bytes beyond the
+/// width of the representation are shifted out.
+fn repr_from_be_bytes<R: PrimeFieldRepr, I: IntoIterator<Item = u8>>(bytes: I) -> R {
+    let mut repr = R::from(0);
+    for byte in bytes {
+        for _ in 0..8 {
+            repr.mul2();
+        }
+        repr.add_nocarry(&R::from(byte as u64));
+    }
+
+    repr
+}
+
+/// Computes the inverse of `a` modulo `m` by the extended Euclidean algorithm,
+/// if it exists.
+fn inverse_mod_u64(a: u64, m: u64) -> Option<u64> {
+    let (mut t, mut new_t) = (0i128, 1i128);
+    let (mut r, mut new_r) = (m as i128, a as i128);
+
+    while new_r != 0 {
+        let q = r / new_r;
+
+        let tmp = t - q * new_t;
+        t = new_t;
+        new_t = tmp;
+
+        let tmp = r - q * new_r;
+        r = new_r;
+        new_r = tmp;
+    }
+
+    if r != 1 {
+        None
+    } else if t < 0 {
+        Some((t + (m as i128)) as u64)
+    } else {
+        Some(t as u64)
+    }
+}
+
+#[test]
+fn test_inverse_mod_u64() {
+    assert_eq!(inverse_mod_u64(3, 7), Some(5));
+    assert_eq!(inverse_mod_u64(10, 17), Some(12));
+    assert_eq!(inverse_mod_u64(4, 6), None);
+    assert_eq!(inverse_mod_u64(0, 5), None);
+    assert_eq!(inverse_mod_u64(0xfffffffffffffffe, 0xffffffffffffffff), Some(0xfffffffffffffffe));
+}
+
+/// Adapts a `rand::Rng` to `rand_core::RngCore`, so that generators from either
+/// crate can be passed to `Field::random` and `PrimeFieldRepr::random`.
+pub struct RngCompat<'a, R: rand::Rng + 'a>(pub &'a mut R);
+
+impl<'a, R: rand::Rng> rand_core::RngCore for RngCompat<'a, R> {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.0.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Feeds `repr` into `hasher` as `F::canonical_byte_len()` big-endian bytes.
+#[cfg(feature = "digest")]
+fn update_canonical_be<F: PrimeField, H: digest::Update>(hasher: &mut H, repr: &F::Repr) {
+    let mut skip = repr.as_ref().len() * 8 - F::canonical_byte_len();
+
+    for limb in repr.as_ref().iter().rev() {
+        let bytes = limb.to_be_bytes();
+        let start = skip.min(8);
+        hasher.update(&bytes[start..]);
+        skip -= start;
+    }
+}
+
+/// Calculate a - b - borrow, returning the result and modifying
+/// the borrow value.
+#[inline(always)]
+pub const fn sbb(a: u64, b: u64, borrow: &mut u64) -> u64 {
+    let tmp = (1u128 << 64) + (a as u128) - (b as u128) - (*borrow as u128);
+
+    *borrow = if tmp >> 64 == 0 { 1 } else { 0 };
+
+    tmp as u64
+}
+
+/// Calculate a + b + carry, returning the sum and modifying the
+/// carry value.
+#[inline(always)]
+pub const fn adc(a: u64, b: u64, carry: &mut u64) -> u64 {
+    let tmp = (a as u128) + (b as u128) + (*carry as u128);
+
+    *carry = (tmp >> 64) as u64;
+
+    tmp as u64
+}
+
+/// Calculate a + (b * c) + carry, returning the least significant digit
 /// and setting carry to the most significant digit.
 #[inline(always)]
-pub fn mac_with_carry(a: u64, b: u64, c: u64, carry: &mut u64) -> u64 {
+pub const fn mac_with_carry(a: u64, b: u64, c: u64, carry: &mut u64) -> u64 {
     let tmp = (a as u128) + (b as u128) * (c as u128) + (*carry as u128);
 
     *carry = (tmp >> 64) as u64;
 
     tmp as u64
 }
+
+/// Calculate a + b + carry over 32-bit words, returning the sum and modifying
+/// the carry value.
+#[inline(always)]
+pub const fn adc32(a: u32, b: u32, carry: &mut u32) -> u32 {
+    let tmp = (a as u64) + (b as u64) + (*carry as u64);
+
+    *carry = (tmp >> 32) as u32;
+
+    tmp as u32
+}
+
+/// Calculate a + (b * c) + carry over 32-bit words, returning the least
+/// significant digit and setting carry to the most significant digit.
+#[inline(always)]
+pub const fn mac_with_carry32(a: u32, b: u32, c: u32, carry: &mut u32) -> u32 {
+    let tmp = (a as u64) + (b as u64) * (c as u64) + (*carry as u64);
+
+    *carry = (tmp >> 32) as u32;
+
+    tmp as u32
+}
@@ -0,0 +1,2275 @@
+extern crate rand;
+extern crate num_bigint;
+extern crate rand_xorshift;
+extern crate sha2;
+#[macro_use]
+extern crate ff;
+
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use num_bigint::BigUint;
+use rand::{Rand, SeedableRng, XorShiftRng};
+
+mod fs {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+
+    // A 254-bit safe prime p = 2q + 1, so that gcd(3, p - 1) = 1.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "20938951530444219524351370703484088351240057006041559427763410976366137328807"]
+    #[PrimeFieldGenerator = "5"]
+    pub struct Fs(FsRepr);
+}
+
+use fs::{Fs, FsRepr};
+
+mod fr {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+
+    // The BLS12-381 scalar field.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "52435875175126190479447740508185965837690552500527637822603658699938581184513"]
+    #[PrimeFieldGenerator = "7"]
+    pub struct Fr(FrRepr);
+}
+
+use fr::{Fr, FrRepr};
+
+mod fr32 {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+
+    // The BLS12-381 scalar field, with arithmetic over 32-bit words.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "52435875175126190479447740508185965837690552500527637822603658699938581184513"]
+    #[PrimeFieldGenerator = "7"]
+    #[PrimeFieldReprLimbSize = "32"]
+    pub struct Fr32(Fr32Repr);
+}
+
+use fr32::{Fr32, Fr32Repr};
+
+mod f25519 {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+
+    // The field of integers modulo 2^255 - 19, where the modulus is 5 mod 8.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "57896044618658097711785492504343953926634992332820282019728792003956564819949"]
+    #[PrimeFieldGenerator = "2"]
+    pub struct F25519(F25519Repr);
+}
+
+use f25519::F25519;
+
+mod f9 {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+
+    // A 254-bit prime p = 8q + 1, which is 9 mod 16.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "20417577719133661098320350778127092253594567460171791926289619735309098383177"]
+    #[PrimeFieldGenerator = "3"]
+    pub struct F9(F9Repr);
+}
+
+use f9::F9;
+
+mod f7 {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+
+    // A tiny field, so that sampling zero is not negligible.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "7"]
+    #[PrimeFieldGenerator = "3"]
+    pub struct F7(F7Repr);
+}
+
+use f7::F7;
+
+mod fr_named {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+
+    // The same field as Fr, wrapping its representation in a named field.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "52435875175126190479447740508185965837690552500527637822603658699938581184513"]
+    #[PrimeFieldGenerator = "7"]
+    pub struct FrNamed { inner: FrNamedRepr }
+}
+
+use fr_named::{FrNamed, FrNamedRepr};
+
+fn test_rng() -> XorShiftRng {
+    XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654])
+}
+
+fn repr_to_biguint(a: &FrRepr) -> BigUint {
+    let mut bytes = vec![];
+    a.write_le(&mut bytes).unwrap();
+    BigUint::from_bytes_le(&bytes)
+}
+
+fn biguint_to_repr(a: &BigUint) -> FrRepr {
+    let mut bytes = a.to_bytes_le();
+    bytes.resize(32, 0);
+    let mut repr = FrRepr::default();
+    repr.read_le(&bytes[..]).unwrap();
+    repr
+}
+
+#[test]
+fn test_nth_root() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fs::rand(&mut rng);
+
+        let root = a.nth_root(3).unwrap();
+        assert_eq!(root.pow([3]), a);
+
+        let root = a.nth_root(1).unwrap();
+        assert_eq!(root, a);
+
+        // p - 1 is even, so squaring is not a permutation.
+        assert!(a.nth_root(2).is_none());
+        assert!(a.nth_root(0).is_none());
+    }
+}
+
+#[test]
+fn test_write_repr_into() {
+    let mut rng = test_rng();
+
+    let mut buf = FsRepr::default();
+    for _ in 0..100 {
+        let a = Fs::rand(&mut rng);
+
+        a.write_repr_into(&mut buf);
+        assert_eq!(buf, a.into_repr());
+    }
+}
+
+const SEVEN: Fs = field_const!(Fs, 7);
+const MAX: Fs = field_const!(Fs, 0xffffffffffffffff);
+
+#[test]
+fn test_field_const() {
+    assert_eq!(SEVEN, Fs::from_repr(FsRepr::from(7)).unwrap());
+    assert_eq!(MAX, Fs::from_repr(FsRepr::from(0xffffffffffffffff)).unwrap());
+    assert_eq!(field_const!(Fs, 0), Fs::zero());
+    assert_eq!(field_const!(Fs, 1), Fs::one());
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_random_nonresidue() {
+    use ff::{PrimeFieldRepr, SqrtField};
+
+    let mut rng = test_rng();
+    let mut rng = ff::RngCompat(&mut rng);
+
+    let mut minus_one = Fs::one();
+    minus_one.negate();
+
+    let mut exp = Fs::char();
+    exp.div2();
+
+    for _ in 0..100 {
+        let a = Fs::random_nonresidue(&mut rng);
+
+        assert_eq!(a.pow(exp), minus_one);
+        assert!(a.sqrt().is_none());
+    }
+}
+
+#[test]
+fn test_limb_size_32() {
+    use ff::SqrtField;
+
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let b = Fr::rand(&mut rng);
+        let a32 = Fr32::from_repr(Fr32Repr(a.into_repr().0)).unwrap();
+        let b32 = Fr32::from_repr(Fr32Repr(b.into_repr().0)).unwrap();
+
+        assert_eq!(Fr::from_repr(a.into_repr()).unwrap(), a);
+        assert_eq!(Fr32::from_repr(a32.into_repr()).unwrap(), a32);
+
+        let mut ab = a;
+        ab.mul_assign(&b);
+        let mut ab32 = a32;
+        ab32.mul_assign(&b32);
+        assert_eq!(ab.into_repr().0, ab32.into_repr().0);
+
+        let mut sq = a;
+        sq.square();
+        let mut sq32 = a32;
+        sq32.square();
+        assert_eq!(sq.into_repr().0, sq32.into_repr().0);
+
+        assert_eq!(a.inverse().unwrap().into_repr().0, a32.inverse().unwrap().into_repr().0);
+        assert_eq!(a.sqrt().map(|r| r.into_repr().0), a32.sqrt().map(|r| r.into_repr().0));
+    }
+
+    assert_eq!(Fr::one().into_repr(), FrRepr::from(1));
+    assert_eq!(Fr32::one().into_repr(), Fr32Repr::from(1));
+}
+
+#[test]
+fn test_canonicalize() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let b = Fr::rand(&mut rng);
+
+        let mut c = a;
+        c.add_assign(&b);
+        let repr = c.into_repr();
+
+        let mut d = c;
+        d.canonicalize();
+        assert_eq!(d, c);
+        assert_eq!(d.into_repr(), repr);
+
+        d.canonicalize();
+        assert_eq!(d.into_repr(), repr);
+    }
+}
+
+#[test]
+fn test_split_at_bit() {
+    let mut rng = test_rng();
+
+    for _ in 0..20 {
+        let a = FrRepr::rand(&mut rng);
+
+        for k in 0..260 {
+            let (lo, mut hi) = a.split_at_bit(k);
+            assert!(lo.num_bits() <= k);
+            assert!(hi.num_bits() <= 256u32.saturating_sub(k));
+
+            for _ in 0..k {
+                hi.mul2();
+            }
+            hi.add_nocarry(&lo);
+            assert_eq!(hi, a);
+        }
+    }
+
+    let (lo, hi) = FrRepr::from(0b1011).split_at_bit(2);
+    assert_eq!(lo, FrRepr::from(0b11));
+    assert_eq!(hi, FrRepr::from(0b10));
+}
+
+#[test]
+fn test_two_adic_decomposition() {
+    let mut p = Fr::t();
+    for _ in 0..Fr::s() {
+        p.mul2();
+    }
+    p.add_nocarry(&FrRepr::from(1));
+    assert_eq!(p, Fr::char());
+
+    let mut t = Fr::t_minus_1_over_2();
+    t.mul2();
+    t.add_nocarry(&FrRepr::from(1));
+    assert_eq!(t, Fr::t());
+
+    assert_eq!(Fr::multiplicative_generator().pow(Fr::t()), Fr::root_of_unity());
+}
+
+#[test]
+fn test_modulus_minus_one_over_two() {
+    let mut p_minus_1 = Fr::char();
+    p_minus_1.sub_noborrow(&FrRepr::from(1));
+
+    let mut a = Fr::modulus_minus_one_over_two();
+    a.mul2();
+    a.add_nocarry(&FrRepr::from(1));
+    assert_eq!(a, Fr::char());
+
+    let mut a = Fr::modulus_minus_one_over_two();
+    a.mul2();
+    assert_eq!(a, p_minus_1);
+}
+
+#[test]
+fn test_add_poly() {
+    let mut rng = test_rng();
+
+    let a: Vec<Fr> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+    let b: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+
+    let sum = ff::add_poly(&a, &b);
+    assert_eq!(sum.len(), 5);
+    for i in 0..5 {
+        let mut expected = a[i];
+        if i < 3 {
+            expected.add_assign(&b[i]);
+        }
+        assert_eq!(sum[i], expected);
+    }
+
+    assert_eq!(ff::add_poly(&b, &a), sum);
+    assert_eq!(ff::add_poly(&a, &[]), a);
+    assert!(ff::add_poly::<Fr>(&[], &[]).is_empty());
+}
+
+#[test]
+fn test_sqrt_5_mod_8() {
+    use ff::SqrtField;
+
+    let mut rng = test_rng();
+
+    assert_eq!(F25519::zero().sqrt(), Some(F25519::zero()));
+
+    for _ in 0..100 {
+        let a = F25519::rand(&mut rng);
+        let mut b = a;
+        b.square();
+
+        let root = b.sqrt().unwrap();
+        let mut check = root;
+        check.square();
+        assert_eq!(check, b);
+
+        // The generator is a nonresidue, so this product is too.
+        if !a.is_zero() {
+            b.mul_assign(&F25519::multiplicative_generator());
+            assert!(b.sqrt().is_none());
+        }
+    }
+}
+
+#[test]
+fn test_batch_legendre() {
+    use ff::LegendreSymbol;
+
+    let mut rng = test_rng();
+
+    let mut exp = Fr::char();
+    exp.div2();
+
+    let mut elements: Vec<Fr> = (0..100).map(|_| Fr::rand(&mut rng)).collect();
+    elements.push(Fr::zero());
+    elements.push(Fr::one());
+    elements.push(Fr::multiplicative_generator());
+
+    let symbols = Fr::batch_legendre(&elements);
+    assert_eq!(symbols.len(), elements.len());
+
+    for (a, symbol) in elements.iter().zip(symbols) {
+        let expected = if a.is_zero() {
+            LegendreSymbol::Zero
+        } else if a.pow(exp) == Fr::one() {
+            LegendreSymbol::QuadraticResidue
+        } else {
+            LegendreSymbol::QuadraticNonResidue
+        };
+        assert_eq!(symbol, expected);
+    }
+
+    assert!(Fr::batch_legendre(&[]).is_empty());
+}
+
+#[test]
+fn test_sqrt_9_mod_16() {
+    use ff::SqrtField;
+
+    let mut rng = test_rng();
+
+    assert_eq!(F9::zero().sqrt(), Some(F9::zero()));
+
+    for _ in 0..100 {
+        let a = F9::rand(&mut rng);
+        let mut b = a;
+        b.square();
+
+        let root = b.sqrt().unwrap();
+        let mut check = root;
+        check.square();
+        assert_eq!(check, b);
+
+        if !a.is_zero() {
+            b.mul_assign(&F9::multiplicative_generator());
+            assert!(b.sqrt().is_none());
+        }
+    }
+}
+
+#[test]
+fn test_horner_eval() {
+    let n = |x: u64| Fr::from_repr(FrRepr::from(x)).unwrap();
+
+    // 3 + 2x + 5x^3 at x = 7 is 3 + 14 + 1715.
+    let coeffs = [n(3), n(2), n(0), n(5)];
+    assert_eq!(ff::horner_eval(&coeffs, &n(7)), n(1732));
+    assert_eq!(ff::horner_eval(&coeffs, &Fr::zero()), n(3));
+    assert_eq!(ff::horner_eval(&[], &n(7)), Fr::zero());
+}
+
+#[test]
+fn test_bytes_with_flag() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+
+        for &flag in &[false, true] {
+            let bytes = a.to_bytes_with_flag(flag).unwrap();
+            assert_eq!(bytes.len(), 32);
+            assert_eq!(bytes[0] & 0x80 != 0, flag);
+            assert_eq!(Fr::from_bytes_with_flag(&bytes), Ok((a, flag)));
+        }
+    }
+
+    // The value left after clearing the flag must be canonical.
+    let mut bytes = vec![0xff; 32];
+    assert_eq!(Fr::from_bytes_with_flag(&bytes), Err(ff::PrimeFieldDecodingError::NotInField));
+    bytes[0] = 0x7f;
+    assert_eq!(Fr::from_bytes_with_flag(&bytes), Err(ff::PrimeFieldDecodingError::NotInField));
+
+    assert_eq!(
+        Fr::from_bytes_with_flag(&[0; 31]),
+        Err(ff::PrimeFieldDecodingError::InvalidLength { expected: 32, actual: 31 })
+    );
+    assert_eq!(
+        Fr::from_bytes_with_flag(&[0; 33]),
+        Err(ff::PrimeFieldDecodingError::InvalidLength { expected: 32, actual: 33 })
+    );
+}
+
+#[test]
+fn test_pow_2_pow() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+
+        assert_eq!(a.pow_2_pow(0), a);
+        assert_eq!(a.pow_2_pow(5), a.pow([32]));
+    }
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(format!("{}", Fr::zero()), "0");
+    assert_eq!(format!("{}", Fr::one()), "1");
+    assert_eq!(format!("{}", Fr::from_repr(FrRepr::from(10000000000000000000)).unwrap()), "10000000000000000000");
+    assert_eq!(format!("{}", Fr::from_repr(FrRepr([0, 1, 0, 0])).unwrap()), "18446744073709551616");
+
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+    assert_eq!(
+        format!("{}", minus_one),
+        "52435875175126190479447740508185965837690552500527637822603658699938581184512"
+    );
+    assert_eq!(format!("{}", F25519::one()), "1");
+}
+
+#[test]
+fn test_from_bytes() {
+    use ff::{ByteOrder, PrimeFieldDecodingError};
+
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+
+        let mut be = a.to_bytes_with_flag(false).unwrap();
+        assert_eq!(Fr::from_bytes(&be, ByteOrder::BigEndian), Ok(a));
+
+        be.reverse();
+        assert_eq!(Fr::from_bytes(&be, ByteOrder::LittleEndian), Ok(a));
+    }
+
+    let mut bytes = [0u8; 32];
+    bytes[0] = 0x12;
+    bytes[31] = 0x34;
+    assert_eq!(
+        Fr::from_bytes(&bytes, ByteOrder::BigEndian),
+        Ok(Fr::from_repr(FrRepr([0x34, 0, 0, 0x1200000000000000])).unwrap())
+    );
+    assert_eq!(
+        Fr::from_bytes(&bytes, ByteOrder::LittleEndian),
+        Ok(Fr::from_repr(FrRepr([0x12, 0, 0, 0x3400000000000000])).unwrap())
+    );
+
+    assert_eq!(
+        Fr::from_bytes(&[0xff; 32], ByteOrder::BigEndian),
+        Err(PrimeFieldDecodingError::NotInField)
+    );
+    assert_eq!(
+        Fr::from_bytes(&[0; 31], ByteOrder::LittleEndian),
+        Err(PrimeFieldDecodingError::InvalidLength { expected: 32, actual: 31 })
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    extern crate serde_json;
+
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json.len(), 66);
+        assert_eq!(serde_json::from_str::<Fr>(&json).unwrap(), a);
+    }
+
+    assert_eq!(
+        serde_json::to_string(&Fr::one()).unwrap(),
+        "\"0000000000000000000000000000000000000000000000000000000000000001\""
+    );
+
+    // The modulus itself is out of range.
+    let modulus = "\"73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001\"";
+    assert!(serde_json::from_str::<Fr>(modulus).is_err());
+    assert!(serde_json::from_str::<Fr>("\"01\"").is_err());
+    assert!(serde_json::from_str::<Fr>("\"zz\"").is_err());
+}
+
+#[test]
+fn test_repr_read_write() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = FrRepr::rand(&mut rng);
+
+        let mut be = vec![];
+        a.write_be(&mut be).unwrap();
+        assert_eq!(be.len(), 32);
+        let mut b = FrRepr::default();
+        b.read_be(&be[..]).unwrap();
+        assert_eq!(a, b);
+
+        let mut le = vec![];
+        a.write_le(&mut le).unwrap();
+        let mut c = FrRepr::default();
+        c.read_le(&le[..]).unwrap();
+        assert_eq!(a, c);
+
+        le.reverse();
+        assert_eq!(le, be);
+    }
+
+    let mut be = vec![];
+    FrRepr::from(0x0102).write_be(&mut be).unwrap();
+    assert_eq!(&be[30..], &[1, 2]);
+
+    let mut a = FrRepr::default();
+    assert!(a.read_be(&[0u8; 31][..]).is_err());
+    assert!(a.read_le(&[0u8; 31][..]).is_err());
+}
+
+#[test]
+fn test_sub_assign_edge_cases() {
+    // Subtraction by comparing and adding the modulus first.
+    fn reference(a: Fr, b: Fr) -> FrRepr {
+        let mut a = a.into_repr();
+        let b = b.into_repr();
+        if b > a {
+            a.add_nocarry(&Fr::char());
+        }
+        a.sub_noborrow(&b);
+        a
+    }
+
+    let mut rng = test_rng();
+
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+    let mut elements = vec![Fr::zero(), Fr::one(), minus_one];
+    elements.extend((0..20).map(|_| Fr::rand(&mut rng)));
+
+    for &a in &elements {
+        for &b in &elements {
+            let mut c = a;
+            c.sub_assign(&b);
+            assert_eq!(c.into_repr(), reference(a, b));
+        }
+
+        let mut c = a;
+        c.sub_assign(&a);
+        assert!(c.is_zero());
+
+        let mut c = Fr::zero();
+        c.sub_assign(&a);
+        let mut expected = a;
+        expected.negate();
+        assert_eq!(c, expected);
+    }
+}
+
+#[test]
+fn test_repr_value_cmp() {
+    use std::cmp::Ordering;
+
+    fn sorted<F: PrimeField>(mut reprs: Vec<F::Repr>) -> Vec<F::Repr> {
+        reprs.sort_by(F::repr_value_cmp);
+        reprs
+    }
+
+    let a = FrRepr::from(5);
+    let b = FrRepr([0, 1, 0, 0]);
+    let c = FrRepr([0, 0, 0, 1]);
+
+    assert_eq!(Fr::repr_value_cmp(&a, &b), Ordering::Less);
+    assert_eq!(Fr::repr_value_cmp(&c, &b), Ordering::Greater);
+    assert_eq!(Fr::repr_value_cmp(&b, &b), Ordering::Equal);
+    assert_eq!(sorted::<Fr>(vec![c, a, b]), vec![a, b, c]);
+}
+
+#[test]
+fn test_batch_inversion() {
+    let mut rng = test_rng();
+
+    let mut elements: Vec<Fr> = (0..1000).map(|_| Fr::rand(&mut rng)).collect();
+    elements[0] = Fr::zero();
+    elements[500] = Fr::zero();
+    elements[999] = Fr::zero();
+
+    let mut inverted = elements.clone();
+    ff::batch_inversion(&mut inverted);
+
+    for (a, inv) in elements.iter().zip(inverted.iter()) {
+        match a.inverse() {
+            Some(expected) => assert_eq!(*inv, expected),
+            None => assert!(inv.is_zero())
+        }
+    }
+
+    let mut empty: [Fr; 0] = [];
+    ff::batch_inversion(&mut empty);
+
+    let mut zeros = [Fr::zero(); 3];
+    ff::batch_inversion(&mut zeros);
+    assert_eq!(zeros, [Fr::zero(); 3]);
+}
+
+#[test]
+fn test_from_repr_zero() {
+    assert_eq!(Fr::from_repr(FrRepr::default()), Ok(Fr::zero()));
+    assert_eq!(Fr32::from_repr(Fr32Repr::default()), Ok(Fr32::zero()));
+    assert!(Fr::from_repr(FrRepr::default()).unwrap().into_repr().is_zero());
+    assert_eq!(Fr::from_repr(FrRepr::from(1)), Ok(Fr::one()));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_sampling_bias_estimate() {
+    let mut rng = test_rng();
+    let mut rng = ff::RngCompat(&mut rng);
+
+    let stat = Fr::sampling_bias_estimate(&mut rng, 100000);
+    assert!(stat < 1.5, "{}", stat);
+
+    let stat = Fs::sampling_bias_estimate(&mut rng, 100000);
+    assert!(stat < 1.5, "{}", stat);
+
+    let stat = F25519::sampling_bias_estimate(&mut rng, 100000);
+    assert!(stat < 1.5, "{}", stat);
+
+    let stat = Fr32::sampling_bias_estimate(&mut rng, 100000);
+    assert!(stat < 1.5, "{}", stat);
+}
+
+#[test]
+fn test_pow_vartime() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let exp = FrRepr::rand(&mut rng);
+
+        assert_eq!(a.pow(exp), a.pow_vartime(exp));
+        assert_eq!(a.pow([0]), Fr::one());
+        assert_eq!(a.pow_vartime([0]), Fr::one());
+        assert_eq!(a.pow([1]), a);
+    }
+}
+
+// Wall-clock comparisons are unreliable under load, so this only runs when
+// requested with `cargo test -- --ignored`.
+#[test]
+#[ignore]
+fn test_pow_timing_smoke() {
+    use std::time::{Duration, Instant};
+
+    // Compares the fastest of many interleaved runs for exponents of the same
+    // length but very different Hamming weights. The vartime exponentiation
+    // skips the multiplications for the sparse exponent, which takes about
+    // half the time.
+    fn time<F: Fn()>(f: F) -> Duration {
+        let start = Instant::now();
+        f();
+        start.elapsed()
+    }
+
+    let a = Fr::rand(&mut test_rng());
+    let sparse = [0, 0, 0, 0];
+    let dense = [!0, !0, !0, !0];
+
+    let mut fastest_sparse = Duration::from_secs(1000);
+    let mut fastest_dense = Duration::from_secs(1000);
+    for _ in 0..30 {
+        fastest_sparse = fastest_sparse.min(time(|| for _ in 0..10 { a.pow(sparse); }));
+        fastest_dense = fastest_dense.min(time(|| for _ in 0..10 { a.pow(dense); }));
+    }
+
+    let (x, y) = (fastest_sparse.as_secs_f64(), fastest_dense.as_secs_f64());
+    let ratio = x.max(y) / x.min(y);
+    assert!(ratio < 1.5, "{}", ratio);
+}
+
+// Wraps Fr, counting the squarings and multiplications performed on this thread,
+// so that exponentiations can be checked to do the same work for every exponent.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct CountingFr(Fr);
+
+thread_local! {
+    static FIELD_OPS: std::cell::Cell<(usize, usize)> = const { std::cell::Cell::new((0, 0)) };
+}
+
+fn count_field_ops<F: FnOnce()>(f: F) -> (usize, usize) {
+    FIELD_OPS.with(|ops| ops.set((0, 0)));
+    f();
+    FIELD_OPS.with(|ops| ops.get())
+}
+
+impl Rand for CountingFr {
+    fn rand<R: rand::Rng>(rng: &mut R) -> Self {
+        CountingFr(Fr::rand(rng))
+    }
+}
+
+impl Field for CountingFr {
+    fn zero() -> Self { CountingFr(Fr::zero()) }
+    fn one() -> Self { CountingFr(Fr::one()) }
+    fn random<R: ff::rand_core::RngCore + ?Sized>(rng: &mut R) -> Self { CountingFr(Fr::random(rng)) }
+    fn is_zero(&self) -> bool { self.0.is_zero() }
+    fn double(&mut self) { self.0.double() }
+    fn negate(&mut self) { self.0.negate() }
+    fn add_assign(&mut self, other: &Self) { self.0.add_assign(&other.0) }
+    fn sub_assign(&mut self, other: &Self) { self.0.sub_assign(&other.0) }
+    fn inverse(&self) -> Option<Self> { self.0.inverse().map(CountingFr) }
+    fn frobenius_map(&mut self, power: usize) { self.0.frobenius_map(power) }
+
+    fn square(&mut self) {
+        FIELD_OPS.with(|ops| { let (s, m) = ops.get(); ops.set((s + 1, m)) });
+        self.0.square();
+    }
+
+    fn mul_assign(&mut self, other: &Self) {
+        FIELD_OPS.with(|ops| { let (s, m) = ops.get(); ops.set((s, m + 1)) });
+        self.0.mul_assign(&other.0);
+    }
+}
+
+#[test]
+fn test_pow_operation_count() {
+    let mut rng = test_rng();
+    let a = CountingFr::rand(&mut rng);
+
+    // Every exponent of the same length takes the same squarings and
+    // multiplications, whatever its bits.
+    let expected = count_field_ops(|| { a.pow([0, 0, 0, 0]); });
+    assert_eq!(expected, (256, 256));
+    assert_eq!(count_field_ops(|| { a.pow([!0, !0, !0, !0]); }), expected);
+    for _ in 0..10 {
+        let exp: [u64; 4] = Rand::rand(&mut rng);
+        assert_eq!(count_field_ops(|| { a.pow(exp); }), expected);
+        assert_eq!(a.pow(exp).0, a.0.pow(exp));
+    }
+
+    let expected = count_field_ops(|| { a.pow_montgomery_ladder([0, 0, 0, 0]); });
+    assert_eq!(expected, (256, 256));
+    assert_eq!(count_field_ops(|| { a.pow_montgomery_ladder([!0, !0, !0, !0]); }), expected);
+    for _ in 0..10 {
+        let exp: [u64; 4] = Rand::rand(&mut rng);
+        assert_eq!(count_field_ops(|| { a.pow_montgomery_ladder(exp); }), expected);
+        assert_eq!(a.pow_montgomery_ladder(exp).0, a.0.pow_montgomery_ladder(exp));
+    }
+}
+
+#[test]
+fn test_mul_by_nonresidue() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+
+        let mut expected = a;
+        expected.mul_assign(&Fr::multiplicative_generator());
+        assert_eq!(a.mul_by_nonresidue(), expected);
+    }
+
+    assert_eq!(Fr::one().mul_by_nonresidue(), Fr::multiplicative_generator());
+}
+
+// The references are taken to exercise the impls for references.
+#[allow(clippy::op_ref)]
+#[test]
+fn test_ops() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let b = Fr::rand(&mut rng);
+        let c = Fr::rand(&mut rng);
+        let d = Fr::rand(&mut rng);
+
+        let mut expected = b;
+        expected.mul_assign(&c);
+        expected.add_assign(&a);
+        expected.sub_assign(&d);
+
+        assert_eq!(a + b * c - d, expected);
+        assert_eq!(&a + &(&b * &c) - &d, expected);
+        assert_eq!(a + &(b * &c) - d, expected);
+
+        let mut e = a;
+        e += b * c;
+        e -= &d;
+        assert_eq!(e, expected);
+
+        e *= a;
+        e *= &b;
+        expected.mul_assign(&a);
+        expected.mul_assign(&b);
+        assert_eq!(e, expected);
+
+        let mut neg = a;
+        neg.negate();
+        assert_eq!(-a, neg);
+        assert_eq!(-&a, neg);
+        assert_eq!(a + -a, Fr::zero());
+    }
+}
+
+#[test]
+fn test_low_u128() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let x = (u64::rand(&mut rng) as u128) << 64 | u64::rand(&mut rng) as u128;
+        assert_eq!(FrRepr::from_u128(x).low_u128(), x);
+    }
+
+    assert_eq!(FrRepr([1, 2, 3, 4]).low_u128(), 2 << 64 | 1);
+    assert_eq!(FrRepr::from(7).low_u128(), 7);
+}
+
+#[test]
+fn test_sum_product() {
+    let n = |x: u64| Fr::from_repr(FrRepr::from(x)).unwrap();
+
+    assert_eq!((1..6).map(n).product::<Fr>(), n(120));
+    assert_eq!((1..6).map(n).sum::<Fr>(), n(15));
+
+    let elements: Vec<Fr> = (1..6).map(n).collect();
+    assert_eq!(elements.iter().product::<Fr>(), n(120));
+    assert_eq!(elements.iter().sum::<Fr>(), n(15));
+
+    assert_eq!(Vec::<Fr>::new().into_iter().sum::<Fr>(), Fr::zero());
+    assert_eq!(Vec::<Fr>::new().into_iter().product::<Fr>(), Fr::one());
+}
+
+#[test]
+fn test_from_str() {
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+
+    assert_eq!(
+        Fr::from_str("52435875175126190479447740508185965837690552500527637822603658699938581184512"),
+        Ok(minus_one)
+    );
+    assert_eq!(
+        Fr::from_str("52435875175126190479447740508185965837690552500527637822603658699938581184514"),
+        Ok(Fr::one())
+    );
+    assert_eq!(Fr::from_str("0"), Ok(Fr::zero()));
+    assert_eq!(Fr::from_str("007").ok(), Fr::from_repr(FrRepr::from(7)).ok());
+    assert_eq!(Fr::from_str("18446744073709551616").ok(), Fr::from_repr(FrRepr([0, 1, 0, 0])).ok());
+
+    assert!(Fr::from_str("").is_err());
+    assert!(Fr::from_str("-1").is_err());
+    assert!(Fr::from_str("12a").is_err());
+    assert!(Fr::from_str(" 1").is_err());
+
+    let mut rng = test_rng();
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        assert_eq!(Fr::from_str(&format!("{}", a)), Ok(a));
+    }
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_first_difference() {
+    let mut rng = test_rng();
+
+    let a: Vec<Fr> = (0..10).map(|_| Fr::rand(&mut rng)).collect();
+    let mut b = a.clone();
+    assert_eq!(ff::first_difference(&a, &b), None);
+
+    b[7].add_assign(&Fr::one());
+    assert_eq!(ff::first_difference(&a, &b), Some(7));
+    b[3] = Fr::zero();
+    assert_eq!(ff::first_difference(&a, &b), Some(3));
+
+    assert_eq!(ff::first_difference(&a, &a[..4]), Some(4));
+    assert_eq!(ff::first_difference(&a[..4], &a), Some(4));
+    assert_eq!(ff::first_difference::<Fr>(&[], &[]), None);
+}
+
+#[test]
+fn test_from_u64() {
+    let mut expected = Fr::zero();
+    for _ in 0..7 {
+        expected.add_assign(&Fr::one());
+    }
+    assert_eq!(Fr::from(7u64), expected);
+    assert_eq!(Fr::from_u64(7), expected);
+    assert_eq!(Fr32::from(7u64), Fr32::from_repr(Fr32Repr::from(7)).unwrap());
+
+    assert_eq!(Fr::from_u64(0), Fr::zero());
+    assert_eq!(Fr::from_u64(1), Fr::one());
+    assert_eq!(Fr::from_u64(!0), Fr::from_repr(FrRepr::from(!0)).unwrap());
+}
+
+#[test]
+fn test_invert_and_mul() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let b = Fr::rand(&mut rng);
+
+        let mut expected = b;
+        expected.mul_assign(&a.inverse().unwrap());
+        assert_eq!(a.invert_and_mul(&b), Some(expected));
+        assert_eq!(Fr::zero().invert_and_mul(&b), None);
+    }
+}
+
+#[test]
+fn test_repr_bits() {
+    let a = FrRepr::from(0b1011);
+    assert_eq!(a.bits().collect::<Vec<_>>(), [true, false, true, true]);
+    assert_eq!(a.bits_le().collect::<Vec<_>>(), [true, true, false, true]);
+    assert_eq!(FrRepr::from(0).bits().count(), 0);
+
+    let mut rng = test_rng();
+    for _ in 0..100 {
+        let a = FrRepr::rand(&mut rng);
+
+        // Rebuild the number from its bits.
+        let mut b = FrRepr::from(0);
+        for bit in a.bits() {
+            b.mul2();
+            if bit {
+                b.add_nocarry(&FrRepr::from(1));
+            }
+        }
+        assert_eq!(a, b);
+
+        let mut le: Vec<bool> = a.bits_le().collect();
+        le.reverse();
+        assert_eq!(le, a.bits().collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn test_add_mod() {
+    let mut rng = test_rng();
+
+    for _ in 0..1000 {
+        // Moduli with the top bit set exercise the carry out of the representation.
+        let mut modulus = FrRepr::rand(&mut rng);
+        if bool::rand(&mut rng) {
+            modulus.0[3] |= 1 << 63;
+        }
+        let m = repr_to_biguint(&modulus);
+        if m == BigUint::from(0u64) {
+            continue;
+        }
+
+        let a = repr_to_biguint(&FrRepr::rand(&mut rng)) % &m;
+        let b = repr_to_biguint(&FrRepr::rand(&mut rng)) % &m;
+
+        let mut c = biguint_to_repr(&a);
+        c.add_mod(&biguint_to_repr(&b), &modulus);
+        assert_eq!(c, biguint_to_repr(&((a + b) % &m)));
+    }
+}
+
+#[test]
+fn test_shr_shl() {
+    let mut a = FrRepr::from(1);
+    a.shl(100);
+    assert_eq!(a, FrRepr([0, 1 << 36, 0, 0]));
+    a.shr(100);
+    assert_eq!(a, FrRepr::from(1));
+
+    let mut rng = test_rng();
+    let width = BigUint::from(1u64) << 256;
+
+    for _ in 0..100 {
+        let a = FrRepr::rand(&mut rng);
+        let x = repr_to_biguint(&a);
+
+        for n in (0..270).chain(Some(!0)) {
+            let mut b = a;
+            b.shr(n);
+            let expected = if n < 256 { &x >> n as usize } else { BigUint::from(0u64) };
+            assert_eq!(b, biguint_to_repr(&expected));
+
+            let mut b = a;
+            b.shl(n);
+            let expected = if n < 256 { (&x << n as usize) % &width } else { BigUint::from(0u64) };
+            assert_eq!(b, biguint_to_repr(&expected));
+        }
+    }
+}
+
+#[test]
+fn test_bit_access() {
+    let mut a = FrRepr::default();
+    a.set_bit(0, true);
+    a.set_bit(65, true);
+    assert!(a.bit(0));
+    assert!(a.bit(65));
+    assert!(!a.bit(1));
+    assert!(!a.bit(64));
+    assert_eq!(a, FrRepr([1, 2, 0, 0]));
+    assert_eq!(a.num_bits(), 66);
+
+    a.set_bit(65, false);
+    assert_eq!(a, FrRepr::from(1));
+    assert_eq!(a.num_bits(), 1);
+
+    assert!(!a.bit(256));
+    assert!(!a.bit(!0));
+
+    let mut rng = test_rng();
+    for _ in 0..10 {
+        let a = FrRepr::rand(&mut rng);
+        let bits: Vec<bool> = (0..256).map(|i| a.bit(i)).collect();
+        let mut expected: Vec<bool> = a.bits_le().collect();
+        expected.resize(256, false);
+        assert_eq!(bits, expected);
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_set_bit_out_of_range() {
+    FrRepr::default().set_bit(256, true);
+}
+
+#[test]
+fn test_legendre() {
+    use ff::{LegendreSymbol, SqrtField};
+
+    assert_eq!(Fr::one().legendre(), LegendreSymbol::QuadraticResidue);
+    assert_eq!(Fr::zero().legendre(), LegendreSymbol::Zero);
+    assert_eq!(Fr::multiplicative_generator().legendre(), LegendreSymbol::QuadraticNonResidue);
+
+    let mut rng = test_rng();
+    let elements: Vec<Fr> = (0..100).map(|_| Fr::rand(&mut rng)).collect();
+    let symbols = Fr::batch_legendre(&elements);
+
+    for (a, symbol) in elements.iter().zip(symbols) {
+        assert_eq!(a.legendre(), symbol);
+        assert_eq!(a.sqrt().is_some(), symbol != LegendreSymbol::QuadraticNonResidue);
+    }
+}
+
+#[test]
+fn test_batch_square() {
+    let mut rng = test_rng();
+
+    let elements: Vec<Fr> = (0..100).map(|_| Fr::rand(&mut rng)).collect();
+    let mut squared = elements.clone();
+    Fr::batch_square(&mut squared);
+
+    for (a, sq) in elements.iter().zip(squared.iter()) {
+        let mut expected = *a;
+        expected.square();
+        assert_eq!(*sq, expected);
+    }
+
+    Fr::batch_square(&mut []);
+}
+
+#[test]
+fn test_conditional_negate() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+
+        let mut b = a;
+        b.conditional_negate(true);
+        assert_eq!(b, -a);
+
+        let mut b = a;
+        b.conditional_negate(false);
+        assert_eq!(b, a);
+
+        let a32 = Fr32::rand(&mut rng);
+        let mut b32 = a32;
+        b32.conditional_negate(true);
+        assert_eq!(b32, -a32);
+    }
+
+    for &neg in &[false, true] {
+        let mut zero = Fr::zero();
+        zero.conditional_negate(neg);
+        assert_eq!(zero, Fr::zero());
+        assert!(zero.into_repr().is_zero());
+    }
+}
+
+#[test]
+fn test_from_bytes_be_exact_padded() {
+    use ff::PrimeFieldDecodingError;
+
+    assert_eq!(Fr::repr_byte_len(), 32);
+
+    let mut rng = test_rng();
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let bytes = a.to_bytes_with_flag(false).unwrap();
+        assert_eq!(Fr::from_bytes_be_exact(&bytes), Ok(a));
+        assert_eq!(Fr::from_bytes_be_padded(&bytes), Ok(a));
+    }
+
+    let seven = Fr::from(7u64);
+    let short = [0, 7];
+    assert_eq!(Fr::from_bytes_be_padded(&short), Ok(seven));
+    assert_eq!(Fr::from_bytes_be_padded(&[]), Ok(Fr::zero()));
+    assert_eq!(
+        Fr::from_bytes_be_exact(&short),
+        Err(PrimeFieldDecodingError::InvalidLength { expected: 32, actual: 2 })
+    );
+
+    let mut long = vec![0u8; 33];
+    long[32] = 7;
+    assert_eq!(
+        Fr::from_bytes_be_exact(&long),
+        Err(PrimeFieldDecodingError::InvalidLength { expected: 32, actual: 33 })
+    );
+    assert_eq!(
+        Fr::from_bytes_be_padded(&long),
+        Err(PrimeFieldDecodingError::InvalidLength { expected: 32, actual: 33 })
+    );
+
+    assert_eq!(Fr::from_bytes_be_padded(&[0xff; 32]), Err(PrimeFieldDecodingError::NotInField));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_exponentiation_test_vectors() {
+    let mut rng = test_rng();
+    let mut rng = ff::RngCompat(&mut rng);
+
+    let vectors = Fr::exponentiation_test_vectors(&mut rng, 100);
+    assert_eq!(vectors.len(), 100);
+
+    for &(base, exp, result) in &vectors {
+        assert_eq!(base.pow([exp]), result);
+
+        // The triples round-trip through the byte encoding.
+        let bytes = base.to_bytes_with_flag(false).unwrap();
+        assert_eq!(Fr::from_bytes_be_exact(&bytes), Ok(base));
+    }
+}
+
+#[test]
+fn test_reduce_boundaries() {
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+    let mut minus_two = minus_one;
+    minus_two.sub_assign(&Fr::one());
+
+    let mut p_minus_2 = Fr::char();
+    p_minus_2.sub_noborrow(&FrRepr::from(2));
+    assert_eq!(minus_two.into_repr(), p_minus_2);
+
+    assert_eq!(minus_one + Fr::one(), Fr::zero());
+    assert_eq!(minus_one + minus_one, minus_two);
+    assert_eq!(minus_two + Fr::one(), minus_one);
+
+    let mut double = minus_one;
+    double.double();
+    assert_eq!(double, minus_two);
+
+    assert_eq!(minus_one * minus_one, Fr::one());
+    assert_eq!((minus_one + Fr::one()).into_repr(), FrRepr::from(0));
+}
+
+#[test]
+fn test_is_reduced_against() {
+    let mut rng = test_rng();
+
+    let modulus = Fr::char();
+    assert!(!modulus.is_reduced_against(&modulus));
+    assert!(FrRepr::from(0).is_reduced_against(&modulus));
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let b = Fr::rand(&mut rng);
+
+        for c in &[a, a + b, a - b, a * b, -a] {
+            assert!(c.into_repr().is_reduced_against(&modulus));
+        }
+    }
+}
+
+#[test]
+fn test_sqrt_canonical() {
+    let mut rng = test_rng();
+
+    assert_eq!(Fr::zero().sqrt_canonical(), Some(Fr::zero()));
+    assert_eq!(Fr::multiplicative_generator().sqrt_canonical(), None);
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let sq = a * a;
+
+        let root = sq.sqrt_canonical().unwrap();
+        assert!(root.into_repr().is_even());
+        assert_eq!(root * root, sq);
+        assert!(root == a || root == -a);
+        assert_eq!((-a * -a).sqrt_canonical(), Some(root));
+
+        let root = (F25519::rand(&mut rng) * F25519::rand(&mut rng)).sqrt_canonical();
+        if let Some(root) = root {
+            assert!(root.into_repr().is_even());
+        }
+    }
+
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_zeroize() {
+    use ff::zeroize::{Zeroize, Zeroizing};
+
+    let mut rng = test_rng();
+
+    let mut a = Fr::rand(&mut rng);
+    assert!(!a.is_zero());
+    a.zeroize();
+    assert!(a.is_zero());
+    assert_eq!(a.into_repr(), FrRepr([0; 4]));
+
+    let mut repr = FrRepr::rand(&mut rng);
+    repr.zeroize();
+    assert_eq!(repr, FrRepr([0; 4]));
+
+    let secret = Zeroizing::new(Fr::rand(&mut rng));
+    assert_eq!(*secret * Fr::one(), *secret);
+}
+
+#[test]
+fn test_hash() {
+    use std::collections::HashMap;
+
+    let mut map = HashMap::new();
+    map.insert(Fr::from(3), "three");
+
+    assert_eq!(map.get(&(Fr::one() + Fr::from(2))), Some(&"three"));
+    assert_eq!(map.get(&Fr::from(4)), None);
+
+    let mut rng = test_rng();
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let b = Fr::rand(&mut rng);
+        map.insert(a + b, "sum");
+        assert_eq!(map.get(&(b + a)), Some(&"sum"));
+    }
+}
+
+#[test]
+fn test_canonical_bytes() {
+    use std::convert::TryFrom;
+
+    let mut rng = test_rng();
+
+    assert_eq!(Fr::canonical_byte_len(), 32);
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let bytes = a.to_bytes();
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(Fr::from_canonical_bytes(&bytes).unwrap(), a);
+        assert_eq!(Fr::try_from(&bytes[..]).unwrap(), a);
+    }
+
+    let mut modulus = vec![];
+    Fr::char().write_be(&mut modulus).unwrap();
+    assert_eq!(Fr::from_canonical_bytes(&modulus), Err(ff::PrimeFieldDecodingError::NotInField));
+
+    assert_eq!(
+        Fr::from_canonical_bytes(&[0; 31]),
+        Err(ff::PrimeFieldDecodingError::InvalidLength { expected: 32, actual: 31 })
+    );
+    assert!(Fr::try_from(&[0; 33][..]).is_err());
+}
+
+#[test]
+fn test_hamming_distance() {
+    assert_eq!(FrRepr::from(0).hamming_distance(&FrRepr::from(0b111)), 3);
+    assert_eq!(FrRepr::from(0b101).hamming_distance(&FrRepr::from(0b110)), 2);
+
+    let mut rng = test_rng();
+    let a = FrRepr::rand(&mut rng);
+    assert_eq!(a.hamming_distance(&a), 0);
+    assert_eq!(a.hamming_distance(&FrRepr([!0; 4])), 256 - a.as_ref().iter().map(|l| l.count_ones()).sum::<u32>());
+}
+
+#[test]
+fn test_random() {
+    use rand_xorshift::XorShiftRng as CoreXorShiftRng;
+    use ff::rand_core::SeedableRng as CoreSeedableRng;
+
+    let mut rng = CoreXorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc, 0xe5,
+    ]);
+
+    // Every element is canonical, and the top limb is spread over the whole range.
+    let mut high = 0;
+    for _ in 0..1000 {
+        let a = Fr::random(&mut rng);
+        assert!(a.into_repr() < Fr::char());
+        if a.into_repr().as_ref()[3] > Fr::char().as_ref()[3] / 2 {
+            high += 1;
+        }
+    }
+    assert!(high > 400 && high < 600);
+
+    // The rand::Rand impls sample through the same path.
+    let mut a = test_rng();
+    let mut b = test_rng();
+    assert_eq!(Fr::rand(&mut a), Fr::random(&mut ff::RngCompat(&mut b)));
+    assert_eq!(FrRepr::rand(&mut a), FrRepr::random(&mut ff::RngCompat(&mut b)));
+}
+
+#[test]
+fn test_inverse_expect() {
+    let a = Fr::from(7);
+    assert_eq!(a.inverse_expect("inverting seven"), a.inverse().unwrap());
+}
+
+#[test]
+#[should_panic(expected = "computing lagrange denominator: cannot invert Fr(0x0000000000000000000000000000000000000000000000000000000000000000)")]
+fn test_inverse_expect_zero() {
+    Fr::zero().inverse_expect("computing lagrange denominator");
+}
+
+#[test]
+fn test_cube_squared_doubled() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let x = Fr::rand(&mut rng);
+
+        assert_eq!(x.squared(), x * x);
+        assert_eq!(x.cube(), x * x * x);
+        assert_eq!(x.doubled(), x + x);
+        assert_eq!(x.squared().doubled().cube(), (x * x + x * x) * (x * x + x * x) * (x * x + x * x));
+    }
+}
+
+#[test]
+fn test_from_u64s_le() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        assert_eq!(Fr::from_u64s_le(a.into_repr().0).unwrap(), a);
+    }
+
+    assert_eq!(Fr::from_u64s_le([7, 0, 0, 0]).unwrap(), Fr::from(7));
+
+    let mut p_minus_1 = Fr::char();
+    p_minus_1.sub_noborrow(&FrRepr::from(1));
+    assert_eq!(Fr::from_u64s_le(p_minus_1.0).unwrap(), Fr::minus_one());
+
+    assert_eq!(Fr::from_u64s_le(Fr::char().0), Err(ff::PrimeFieldDecodingError::NotInField));
+    assert_eq!(Fr::from_u64s_le([!0; 4]), Err(ff::PrimeFieldDecodingError::NotInField));
+}
+
+#[test]
+fn test_butterfly() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let b = Fr::rand(&mut rng);
+        let w = Fr::rand(&mut rng);
+
+        let (mut x, mut y) = (a, b);
+        Fr::butterfly(&mut x, &mut y);
+        assert_eq!(x, a + b);
+        assert_eq!(y, a - b);
+        assert_eq!(x + y, a.doubled());
+        assert_eq!(x - y, b.doubled());
+
+        let (mut x, mut y) = (a, b);
+        Fr::butterfly_twiddle(&mut x, &mut y, &w);
+        assert_eq!(x, a + b * w);
+        assert_eq!(y, a - b * w);
+    }
+}
+
+#[test]
+fn test_mul_by_u64() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let x = Fr::rand(&mut rng);
+
+        let mut a = x;
+        a.mul_by_u64(7);
+        assert_eq!(a, x + x + x + x + x + x + x);
+
+        for &scalar in &[0, 1, 2, 3, u64::MAX, u64::MAX - 1, 1 << 63] {
+            let mut a = x;
+            a.mul_by_u64(scalar);
+            assert_eq!(a, x * Fr::from(scalar));
+
+            let z = F25519::rand(&mut rng);
+            let mut a = z;
+            a.mul_by_u64(scalar);
+            assert_eq!(a, z * F25519::from(scalar));
+
+            let y = Fr32::from_repr(Fr32Repr(x.into_repr().0)).unwrap();
+            let mut a = y;
+            a.mul_by_u64(scalar);
+            assert_eq!(a, y * Fr32::from(scalar));
+        }
+    }
+
+    // The largest element times the largest scalar.
+    for &scalar in &[u64::MAX, u64::MAX - 1, 0x8000000000000001] {
+        let mut a = Fr::minus_one();
+        a.mul_by_u64(scalar);
+        assert_eq!(a, -Fr::from(scalar));
+
+        let mut a = F25519::minus_one();
+        a.mul_by_u64(scalar);
+        assert_eq!(a, -F25519::from(scalar));
+
+        let mut a = F9::minus_one();
+        a.mul_by_u64(scalar);
+        assert_eq!(a, -F9::from(scalar));
+
+        let mut a = Fs::minus_one();
+        a.mul_by_u64(scalar);
+        assert_eq!(a, -Fs::from(scalar));
+    }
+}
+
+#[test]
+fn test_repr_from_limbs() {
+    assert_eq!(FrRepr::from([1, 0, 0, 0]), FrRepr::from(1u64));
+    assert_eq!(FrRepr::from([1, 2, 3, 4]), FrRepr([1, 2, 3, 4]));
+
+    let mut a = FrRepr::from([1, 2, 3, 4]);
+    a.as_mut()[2] = 7;
+    assert_eq!(a.as_ref(), &[1, 2, 7, 4]);
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn test_modulus_biguint() {
+    use std::str::FromStr;
+
+    assert_eq!(
+        Fr::modulus_biguint(),
+        BigUint::from_str("52435875175126190479447740508185965837690552500527637822603658699938581184513").unwrap()
+    );
+    assert_eq!(
+        F25519::modulus_biguint(),
+        BigUint::from_str("57896044618658097711785492504343953926634992332820282019728792003956564819949").unwrap()
+    );
+}
+
+#[test]
+fn test_pow_i64() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let x = Fr::rand(&mut rng);
+        let inv = x.inverse().unwrap();
+
+        assert_eq!(x.pow_i64(0), Some(Fr::one()));
+        assert_eq!(x.pow_i64(1), Some(x));
+        assert_eq!(x.pow_i64(3), Some(x.cube()));
+        assert_eq!(x.pow_i64(-1), Some(inv));
+        assert_eq!(x.pow_i64(-3), Some(inv.cube()));
+        assert_eq!(x.pow_i64(i64::MIN), Some(inv.pow([1u64 << 63])));
+        assert_eq!(x.pow_i64(i64::MAX), Some(x.pow([i64::MAX as u64])));
+    }
+
+    assert_eq!(Fr::zero().pow_i64(-1), None);
+    assert_eq!(Fr::zero().pow_i64(0), Some(Fr::one()));
+    assert_eq!(Fr::zero().pow_i64(2), Some(Fr::zero()));
+}
+
+#[test]
+fn test_from_repr_error() {
+    use ff::PrimeFieldDecodingError;
+
+    fn decode(repr: FrRepr) -> Result<Fr, Box<dyn std::error::Error>> {
+        Ok(Fr::from_repr(repr)?)
+    }
+
+    assert_eq!(Fr::from_repr(Fr::char()), Err(PrimeFieldDecodingError::NotInField));
+    assert_eq!(Fr32::from_repr(Fr32::char()), Err(PrimeFieldDecodingError::NotInField));
+
+    let err = decode(Fr::char()).unwrap_err();
+    assert_eq!(err.to_string(), "value is not less than the field modulus");
+    assert_eq!(decode(FrRepr::from(1)).unwrap(), Fr::one());
+}
+
+#[test]
+fn test_pow_table() {
+    use ff::PowTable;
+
+    let mut rng = test_rng();
+
+    let table = PowTable::new(&Fr::root_of_unity(), 4, 64);
+    for k in 0..Fr::s() {
+        let exp = [1u64 << k];
+        assert_eq!(table.pow_using_table(exp), Fr::root_of_unity().pow(exp));
+    }
+    assert_eq!(table.pow_using_table([1u64 << Fr::s()]), Fr::one());
+
+    for &window in &[1, 3, 5, 8] {
+        let base = Fr::rand(&mut rng);
+        let table = PowTable::new(&base, window, 256);
+
+        assert_eq!(table.pow_using_table([0u64]), Fr::one());
+        for _ in 0..20 {
+            let exp = FrRepr::rand(&mut rng);
+            assert_eq!(table.pow_using_table(exp), base.pow(exp));
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "exponent is too large for the table")]
+fn test_pow_table_too_large() {
+    // Ten bits of exponent round up to three 4-bit windows.
+    ff::PowTable::new(&Fr::from(3), 4, 10).pow_using_table([1u64 << 12]);
+}
+
+#[test]
+fn test_rotate() {
+    let mut rng = test_rng();
+
+    let mut a = FrRepr([0, 0, 0, 1 << 63]);
+    a.rotate_left(1);
+    assert_eq!(a, FrRepr::from(1));
+    a.rotate_right(1);
+    assert_eq!(a, FrRepr([0, 0, 0, 1 << 63]));
+
+    let mut a = FrRepr([1, 2, 3, 4]);
+    a.rotate_left(64);
+    assert_eq!(a, FrRepr([4, 1, 2, 3]));
+    a.rotate_right(128);
+    assert_eq!(a, FrRepr([2, 3, 4, 1]));
+
+    for _ in 0..100 {
+        let a = FrRepr::rand(&mut rng);
+
+        let mut b = a;
+        b.rotate_left(256);
+        assert_eq!(b, a);
+
+        for k in &[0, 1, 7, 63, 64, 65, 200, 255, 300] {
+            let mut b = a;
+            b.rotate_left(*k);
+            assert_eq!(b.as_ref().iter().map(|l| l.count_ones()).sum::<u32>(),
+                       a.as_ref().iter().map(|l| l.count_ones()).sum::<u32>());
+            b.rotate_right(*k);
+            assert_eq!(b, a);
+        }
+    }
+}
+
+#[test]
+fn test_modulus_bytes() {
+    use std::str::FromStr;
+
+    let bytes = Fr::modulus_bytes();
+    assert_eq!(bytes.len(), 32);
+    assert_eq!(
+        BigUint::from_bytes_be(&bytes),
+        BigUint::from_str("52435875175126190479447740508185965837690552500527637822603658699938581184513").unwrap()
+    );
+    assert_eq!(BigUint::from_bytes_be(&bytes), repr_to_biguint(&Fr::char()));
+
+    assert_eq!(
+        BigUint::from_bytes_be(&F25519::modulus_bytes()),
+        BigUint::from_str("57896044618658097711785492504343953926634992332820282019728792003956564819949").unwrap()
+    );
+}
+
+#[test]
+fn test_sum_of_products_pairs() {
+    let mut rng = test_rng();
+
+    for len in 0..20 {
+        let pairs: Vec<(Fr, Fr)> = (0..len).map(|_| (Fr::rand(&mut rng), Fr::rand(&mut rng))).collect();
+
+        let mut expected = Fr::zero();
+        for &(a, b) in &pairs {
+            expected += a * b;
+        }
+
+        assert_eq!(Fr::sum_of_products_pairs(&pairs), expected);
+    }
+}
+
+#[test]
+fn test_validate_parameters() {
+    assert_eq!(Fr::validate_parameters(), Ok(()));
+    assert_eq!(Fr32::validate_parameters(), Ok(()));
+    assert_eq!(Fs::validate_parameters(), Ok(()));
+    assert_eq!(F25519::validate_parameters(), Ok(()));
+    assert_eq!(F9::validate_parameters(), Ok(()));
+}
+
+#[test]
+fn test_hex_formatting() {
+    assert_eq!(format!("{:#x}", Fr::one()), format!("0x{:064x}", 1));
+    assert_eq!(format!("{:x}", Fr::from(0xab)), format!("{:064x}", 0xab));
+    assert_eq!(format!("{:X}", Fr::from(0xab)), format!("{:064X}", 0xab));
+    assert_eq!(
+        format!("{:#x}", Fr::minus_one()),
+        "0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000000"
+    );
+    assert_eq!(
+        format!("{:#X}", Fr::minus_one()),
+        "0x73EDA753299D7D483339D80809A1D80553BDA402FFFE5BFEFFFFFFFF00000000"
+    );
+    assert_eq!(format!("{:?}", Fr::one()), format!("Fr({:#x})", Fr::one()));
+}
+
+#[test]
+fn test_lagrange_interpolate_at() {
+    let mut rng = test_rng();
+
+    for degree in 0..8 {
+        let coeffs: Vec<Fr> = (0..degree + 1).map(|_| Fr::rand(&mut rng)).collect();
+        let points: Vec<Fr> = (0..degree + 1).map(|i| Fr::from(i as u64 + 1)).collect();
+        let values: Vec<Fr> = points.iter().map(|x| ff::horner_eval(&coeffs, x)).collect();
+
+        let target = Fr::rand(&mut rng);
+        assert_eq!(ff::lagrange_interpolate_at(&points, &values, &target), ff::horner_eval(&coeffs, &target));
+
+        // At the points themselves, the values are returned.
+        for (x, y) in points.iter().zip(&values) {
+            assert_eq!(ff::lagrange_interpolate_at(&points, &values, x), *y);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "interpolation points must be distinct")]
+fn test_lagrange_interpolate_at_duplicate_points() {
+    let points = [Fr::from(1), Fr::from(2), Fr::from(1)];
+    let values = [Fr::from(3), Fr::from(4), Fr::from(5)];
+    ff::lagrange_interpolate_at(&points, &values, &Fr::from(9));
+}
+
+#[test]
+fn test_sqrt_ratio() {
+    use ff::{LegendreSymbol, SqrtField};
+
+    fn check<F: PrimeField + SqrtField>(rng: &mut XorShiftRng) {
+        let mut squares = 0;
+        for _ in 0..100 {
+            let num = F::rand(rng);
+            let div = F::rand(rng);
+
+            let (is_square, root) = F::sqrt_ratio(&num, &div);
+            let mut ratio = num;
+            ratio.mul_assign(&div.inverse().unwrap());
+            assert_eq!(is_square, ratio.legendre() == LegendreSymbol::QuadraticResidue);
+
+            let mut lhs = root.squared();
+            lhs.mul_assign(&div);
+            let mut rhs = num;
+            if is_square {
+                squares += 1;
+            } else {
+                rhs.mul_assign(&F::multiplicative_generator());
+            }
+            assert_eq!(lhs, rhs);
+        }
+        assert!(squares > 30 && squares < 70);
+
+        assert_eq!(F::sqrt_ratio(&F::zero(), &F::from_u64(5)), (true, F::zero()));
+        assert_eq!(F::sqrt_ratio(&F::from_u64(5), &F::zero()), (true, F::zero()));
+    }
+
+    let mut rng = test_rng();
+
+    // Moduli which are 3 mod 4, 5 mod 8, 1 mod 8 with s = 32, and 9 mod 16.
+    check::<Fs>(&mut rng);
+    check::<F25519>(&mut rng);
+    check::<Fr>(&mut rng);
+    check::<F9>(&mut rng);
+
+    let (is_square, root) = F25519::sqrt_ratio(&F25519::from(4), &F25519::from(9));
+    assert!(is_square);
+    assert_eq!(root.squared() * F25519::from(9), F25519::from(4));
+}
+
+#[cfg(feature = "subtle")]
+#[test]
+fn test_invert() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let inv = a.invert();
+        assert!(bool::from(inv.is_some()));
+        assert_eq!(inv.unwrap() * a, Fr::one());
+        assert_eq!(inv.unwrap(), a.inverse().unwrap());
+
+        let a32 = Fr32::from_repr(Fr32Repr(a.into_repr().0)).unwrap();
+        assert_eq!(a32.invert().unwrap() * a32, Fr32::one());
+    }
+
+    assert!(bool::from(Fr::zero().invert().is_none()));
+    assert!(bool::from(Fr32::zero().invert().is_none()));
+    assert_eq!(Fr::one().invert().unwrap(), Fr::one());
+}
+
+#[test]
+fn test_mul_wide() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = FrRepr::rand(&mut rng);
+        let b = FrRepr::rand(&mut rng);
+
+        let (lo, hi) = a.mul_wide(&b);
+        let prod = repr_to_biguint(&a) * repr_to_biguint(&b);
+        assert_eq!(repr_to_biguint(&lo) + (repr_to_biguint(&hi) << 256), prod);
+    }
+
+    let (lo, hi) = FrRepr([!0; 4]).mul_wide(&FrRepr([!0; 4]));
+    assert_eq!(lo, FrRepr::from(1));
+    assert_eq!(hi, FrRepr([!0 - 1, !0, !0, !0]));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_random_nonzero_pair() {
+    let mut rng = test_rng();
+    let mut rng = ff::RngCompat(&mut rng);
+
+    for _ in 0..100 {
+        let (a, b) = Fr::random_nonzero_pair(&mut rng);
+        assert!(!a.is_zero());
+        assert!(!b.is_zero());
+        assert_ne!(a, b);
+        assert_eq!(a * b.inverse().unwrap() * b, a);
+    }
+}
+
+#[test]
+fn test_rns() {
+    let mut rng = test_rng();
+
+    // Five primes below 2^64, ..., 2^60, whose product exceeds the modulus.
+    let moduli = [
+        0xffffffffffffffc5,
+        0x7fffffffffffffe7,
+        0x3fffffffffffffc7,
+        0x1fffffffffffffff,
+        0x0fffffffffffffa3
+    ];
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let residues = a.to_rns(&moduli);
+
+        let value = repr_to_biguint(&a.into_repr());
+        for (r, m) in residues.iter().zip(&moduli) {
+            assert_eq!(BigUint::from(*r), &value % BigUint::from(*m));
+        }
+
+        assert_eq!(Fr::from_rns(&residues, &moduli), a);
+    }
+
+    // With moduli whose product is small, the result is the smallest solution.
+    assert_eq!(Fr::from_rns(&[2, 3, 2], &[3, 5, 7]), Fr::from(23));
+}
+
+#[test]
+#[should_panic(expected = "moduli must be pairwise coprime")]
+fn test_rns_not_coprime() {
+    Fr::from_rns(&[1, 1], &[6, 9]);
+}
+
+#[test]
+fn test_from_str_checked() {
+    use ff::FromStrError;
+
+    assert_eq!(Fr::from_str_checked("0"), Ok(Fr::zero()));
+    assert_eq!(Fr::from_str_checked("007"), Ok(Fr::from(7)));
+    assert_eq!(Fr::from_str_checked("18446744073709551616"), Ok(Fr::from_repr(FrRepr([0, 1, 0, 0])).unwrap()));
+    assert_eq!(
+        Fr::from_str_checked("52435875175126190479447740508185965837690552500527637822603658699938581184512"),
+        Ok(Fr::minus_one())
+    );
+
+    assert_eq!(
+        Fr::from_str_checked("52435875175126190479447740508185965837690552500527637822603658699938581184513"),
+        Err(FromStrError::OutOfRange)
+    );
+    assert_eq!(Fr::from_str_checked(&"9".repeat(100)), Err(FromStrError::OutOfRange));
+    assert_eq!(Fr::from_str_checked(""), Err(FromStrError::Empty));
+    assert_eq!(Fr::from_str_checked("0x1f"), Err(FromStrError::InvalidChar));
+    assert_eq!(Fr::from_str_checked("-1"), Err(FromStrError::InvalidChar));
+    assert_eq!(Fr::from_str_checked("１"), Err(FromStrError::InvalidChar));
+
+    let mut rng = test_rng();
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        assert_eq!(Fr::from_str_checked(&a.to_string()), Ok(a));
+    }
+}
+
+#[test]
+fn test_ord() {
+    let mut v = vec![Fr::from(3), Fr::from(1), Fr::from(2)];
+    v.sort();
+    assert_eq!(v, vec![Fr::from(1), Fr::from(2), Fr::from(3)]);
+
+    assert!(Fr::zero() < Fr::one());
+    assert!(Fr::minus_one() > Fr::from(u64::MAX));
+    assert_eq!(Fr::from(5).max(Fr::from(7)), Fr::from(7));
+
+    let mut rng = test_rng();
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let b = Fr::rand(&mut rng);
+        assert_eq!(a.cmp(&b), a.into_repr().cmp(&b.into_repr()));
+    }
+}
+
+#[test]
+fn test_pow_montgomery_ladder() {
+    let mut rng = test_rng();
+
+    for _ in 0..100 {
+        let a = Fr::rand(&mut rng);
+        let e = FrRepr::rand(&mut rng);
+        assert_eq!(a.pow_montgomery_ladder(e), a.pow_vartime(e));
+
+        for &e in &[0u64, 1, 2, 3, u64::MAX] {
+            assert_eq!(a.pow_montgomery_ladder([e]), a.pow_vartime([e]));
+        }
+    }
+
+    assert_eq!(Fr::zero().pow_montgomery_ladder([0u64]), Fr::one());
+    assert_eq!(Fr::zero().pow_montgomery_ladder([5u64]), Fr::zero());
+}
+
+// A quadratic extension of Fr by a square root u of the nonresidue 7, to check
+// that generic code over `Field` treats prime and extension fields uniformly.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Fr2 {
+    c0: Fr,
+    c1: Fr
+}
+
+impl Rand for Fr2 {
+    fn rand<R: rand::Rng>(rng: &mut R) -> Self {
+        Fr2 { c0: Fr::rand(rng), c1: Fr::rand(rng) }
+    }
+}
+
+impl Field for Fr2 {
+    fn zero() -> Self {
+        Fr2 { c0: Fr::zero(), c1: Fr::zero() }
+    }
+
+    fn one() -> Self {
+        Fr2 { c0: Fr::one(), c1: Fr::zero() }
+    }
+
+    fn random<R: ff::rand_core::RngCore + ?Sized>(rng: &mut R) -> Self {
+        Fr2 { c0: Fr::random(rng), c1: Fr::random(rng) }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.c0.is_zero() && self.c1.is_zero()
+    }
+
+    fn square(&mut self) {
+        let tmp = *self;
+        self.mul_assign(&tmp);
+    }
+
+    fn double(&mut self) {
+        self.c0.double();
+        self.c1.double();
+    }
+
+    fn negate(&mut self) {
+        self.c0.negate();
+        self.c1.negate();
+    }
+
+    fn add_assign(&mut self, other: &Self) {
+        self.c0.add_assign(&other.c0);
+        self.c1.add_assign(&other.c1);
+    }
+
+    fn sub_assign(&mut self, other: &Self) {
+        self.c0.sub_assign(&other.c0);
+        self.c1.sub_assign(&other.c1);
+    }
+
+    fn mul_assign(&mut self, other: &Self) {
+        let (a0, a1) = (self.c0, self.c1);
+        self.c0 = a0 * other.c0 + Fr::multiplicative_generator() * a1 * other.c1;
+        self.c1 = a0 * other.c1 + a1 * other.c0;
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        let norm = self.c0.squared() - Fr::multiplicative_generator() * self.c1.squared();
+        norm.inverse().map(|inv| Fr2 { c0: self.c0 * inv, c1: -self.c1 * inv })
+    }
+
+    fn frobenius_map(&mut self, power: usize) {
+        self.c0.frobenius_map(power);
+        self.c1.frobenius_map(power);
+
+        // u^(p^power) = u * 7^((p^power - 1) / 2), which is -u for odd powers.
+        self.c1.mul_assign(&Fr::frobenius_coeff(power));
+        self.c1.conditional_negate(power % 2 == 1);
+    }
+}
+
+#[test]
+fn test_frobenius_map() {
+    fn frobenius<F: Field>(x: &F, power: usize) -> F {
+        let mut res = *x;
+        res.frobenius_map(power);
+        res
+    }
+
+    let mut rng = test_rng();
+
+    for _ in 0..10 {
+        let a = Fr::rand(&mut rng);
+        for power in 0..4 {
+            assert_eq!(frobenius(&a, power), a);
+            assert_eq!(Fr::frobenius_coeff(power), Fr::one());
+        }
+
+        let b = Fr2::rand(&mut rng);
+        assert_eq!(frobenius(&b, 0), b);
+        assert_eq!(frobenius(&b, 1), b.pow_vartime(Fr::char()));
+        assert_eq!(frobenius(&b, 2), b);
+
+        // The norm b * b^p lies in the prime field.
+        let mut norm = frobenius(&b, 1);
+        norm.mul_assign(&b);
+        assert!(norm.c1.is_zero());
+    }
+}
+
+#[test]
+fn test_batch_sqrt() {
+    use ff::SqrtField;
+
+    let mut rng = test_rng();
+
+    // Fs has a modulus that is 3 mod 4, while Fr falls back to Tonelli-Shanks.
+    assert_eq!(Fs::char().as_ref()[0] & 3, 3);
+    assert_eq!(Fr::char().as_ref()[0] & 3, 1);
+
+    let mut elements: Vec<Fs> = (0..50).map(|_| Fs::rand(&mut rng)).collect();
+    elements.push(Fs::zero());
+    elements.push(Fs::one());
+    let roots = Fs::batch_sqrt(&elements);
+    assert_eq!(roots.len(), elements.len());
+    for (a, root) in elements.iter().zip(&roots) {
+        assert_eq!(root.is_some(), a.sqrt().is_some());
+        if let Some(root) = root {
+            assert_eq!(root.squared(), *a);
+        }
+    }
+
+    let elements: Vec<Fr> = (0..50).map(|_| Fr::rand(&mut rng)).collect();
+    assert_eq!(Fr::batch_sqrt(&elements), elements.iter().map(|a| a.sqrt()).collect::<Vec<_>>());
+
+    assert!(Fs::batch_sqrt(&[]).is_empty());
+}
+
+#[test]
+fn test_associated_consts() {
+    const FR_BYTES: usize = Fr::NUM_BITS as usize / 8 + 1;
+    const FR_MODULUS: FrRepr = <Fr as PrimeField>::MODULUS;
+
+    let buf = [0u8; FR_BYTES];
+    assert_eq!(buf.len(), 32);
+
+    assert_eq!(FR_MODULUS, Fr::char());
+    assert_eq!(Fr::NUM_BITS, Fr::num_bits());
+    assert_eq!(Fr::CAPACITY, Fr::capacity());
+    assert_eq!(Fr::CAPACITY, 254);
+    assert_eq!(F25519::NUM_BITS, 255);
+}
+
+#[cfg(feature = "digest")]
+#[test]
+fn test_hash_into() {
+    use sha2::{Digest, Sha256};
+
+    fn digest_of(a: &Fr) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        a.hash_into(&mut hasher);
+        hasher.finalize().to_vec()
+    }
+
+    let a = Fr::from(3);
+    let b = Fr::one() + Fr::from(2);
+    assert_eq!(digest_of(&a), digest_of(&b));
+    assert_ne!(digest_of(&a), digest_of(&Fr::from(4)));
+
+    // The element is hashed as its canonical bytes.
+    let mut rng = test_rng();
+    for _ in 0..10 {
+        let a = Fr::rand(&mut rng);
+        assert_eq!(digest_of(&a), Sha256::digest(a.to_bytes()).to_vec());
+    }
+
+    let mut hasher = Sha256::new();
+    Fr::hash_char_into(&mut hasher);
+    assert_eq!(hasher.finalize().to_vec(), Sha256::digest(Fr::modulus_bytes()).to_vec());
+}
+
+#[test]
+fn test_batch_into_repr() {
+    let mut rng = test_rng();
+
+    let elements: Vec<Fr> = (0..100).map(|_| Fr::rand(&mut rng)).collect();
+    let expected: Vec<FrRepr> = elements.iter().map(|a| a.into_repr()).collect();
+
+    assert_eq!(Fr::batch_into_repr(&elements), expected);
+
+    let mut buf = vec![FrRepr::default(); 100];
+    Fr::batch_write_repr_into(&elements, &mut buf);
+    assert_eq!(buf, expected);
+
+    assert!(Fr::batch_into_repr(&[]).is_empty());
+}
+
+#[test]
+fn test_repr_is_one() {
+    assert!(FrRepr::from(1).is_one());
+    assert!(!FrRepr::from(0).is_one());
+    assert!(!FrRepr::from(3).is_one());
+    assert!(!FrRepr([1, 0, 0, 1]).is_one());
+    assert!(Fr::one().into_repr().is_one());
+    assert!(!Fr::minus_one().into_repr().is_one());
+}
+
+#[test]
+fn test_random_bounded() {
+    let mut a = test_rng();
+    let mut b = test_rng();
+    let mut a = ff::RngCompat(&mut a);
+    let mut b = ff::RngCompat(&mut b);
+
+    assert_eq!(Fr::random_bounded(&mut a, 0), None);
+
+    for _ in 0..1000 {
+        // Each attempt is accepted with probability greater than 1/2, so a
+        // handful of attempts essentially always suffices.
+        let x = Fr::random_bounded(&mut a, 8).unwrap();
+        assert!(x.into_repr() < Fr::char());
+    }
+
+    // With an unbounded budget this consumes the same randomness as `random`.
+    let mut a = test_rng();
+    let mut a = ff::RngCompat(&mut a);
+    for _ in 0..100 {
+        assert_eq!(Fr::random_bounded(&mut a, usize::MAX), Some(Fr::random(&mut b)));
+    }
+}
+
+#[test]
+fn test_repr_add_scaled() {
+    let mut rng = test_rng();
+
+    for _ in 0..1000 {
+        let a = FrRepr::rand(&mut rng);
+        let b = FrRepr::rand(&mut rng);
+        let c = u64::rand(&mut rng);
+
+        let mut res = b;
+        let carry = res.add_scaled(&a, c);
+
+        // a * c fits in one extra limb, which the final addition may bump.
+        let (mut expected, hi) = a.mul_wide(&FrRepr::from(c));
+        let expected_carry = hi.as_ref()[0] + expected.add_nocarry(&b) as u64;
+
+        assert_eq!(res, expected);
+        assert_eq!(carry, expected_carry);
+    }
+
+    let mut res = FrRepr([!0, !0, !0, !0]);
+    assert_eq!(res.add_scaled(&FrRepr([!0, !0, !0, !0]), !0), !0);
+    assert_eq!(res, FrRepr([0, !0, !0, !0]));
+}
+
+#[test]
+fn test_random_nonzero() {
+    let mut rng = test_rng();
+    let mut rng = ff::RngCompat(&mut rng);
+
+    let mut seen = [false; 7];
+    for _ in 0..1000 {
+        let x = F7::random_nonzero(&mut rng);
+        assert!(!x.is_zero());
+        seen[x.into_repr().as_ref()[0] as usize] = true;
+    }
+    assert_eq!(seen, [false, true, true, true, true, true, true]);
+
+    // Zero does actually come up when sampling this field.
+    assert!((0..1000).any(|_| F7::random(&mut rng).is_zero()));
+}
+
+#[test]
+fn test_pow_vartime_long_exponent() {
+    let mut rng = test_rng();
+
+    let p = repr_to_biguint(&Fr::char());
+    let one = BigUint::from(1u64);
+
+    for _ in 0..20 {
+        let a = Fr::rand(&mut rng);
+        let exp: [u64; 8] = Rand::rand(&mut rng);
+
+        let mut exp_bytes = vec![];
+        for limb in &exp {
+            exp_bytes.extend_from_slice(&limb.to_le_bytes());
+        }
+        let e = BigUint::from_bytes_le(&exp_bytes);
+
+        // Square-and-multiply over BigUint, reducing mod p at each step.
+        let base = repr_to_biguint(&a.into_repr());
+        let mut expected = one.clone();
+        for i in (0..e.bits()).rev() {
+            expected = (&expected * &expected) % &p;
+            if (&e >> i) & &one == one {
+                expected = (&expected * &base) % &p;
+            }
+        }
+
+        assert_eq!(repr_to_biguint(&a.pow_vartime(exp).into_repr()), expected);
+        assert_eq!(a.pow(exp), a.pow_vartime(exp));
+
+        // By Fermat's little theorem, the exponent may be reduced mod p - 1.
+        let reduced = biguint_to_repr(&(&e % (&p - &one)));
+        assert_eq!(a.pow_vartime(reduced), a.pow_vartime(exp));
+    }
+}
+
+#[test]
+fn test_capacity_is_exact() {
+    fn check<F: PrimeField>() {
+        let mut bytes = vec![];
+        F::char().write_le(&mut bytes).unwrap();
+        let p = BigUint::from_bytes_le(&bytes);
+        let one = BigUint::from(1u64);
+
+        // capacity() is the largest k with 2^k <= p.
+        let k = F::capacity() as usize;
+        assert!(&one << k <= p);
+        assert!(&one << (k + 1) > p);
+        assert_eq!(F::capacity(), F::num_bits() - 1);
+    }
+
+    check::<Fs>();
+    check::<Fr>();
+    check::<Fr32>();
+    check::<F25519>();
+    check::<F9>();
+    check::<F7>();
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_random_primitive_root_of_unity() {
+    let mut rng = test_rng();
+    let mut rng = ff::RngCompat(&mut rng);
+
+    assert_eq!(Fr::s(), 32);
+    for &log_order in &[0, 1, 2, 5, 17, 31, 32] {
+        for _ in 0..10 {
+            let root = Fr::random_primitive_root_of_unity(&mut rng, log_order).unwrap();
+
+            // The order is exactly 2^log_order.
+            assert_eq!(root.pow_2_pow(log_order), Fr::one());
+            if log_order > 0 {
+                assert!(root.pow_2_pow(log_order - 1) != Fr::one());
+            }
+        }
+    }
+    assert_eq!(Fr::random_primitive_root_of_unity(&mut rng, 0), Some(Fr::one()));
+    assert_eq!(Fr::random_primitive_root_of_unity(&mut rng, 33), None);
+
+    assert_eq!(F7::random_primitive_root_of_unity(&mut rng, 1), Some(F7::minus_one()));
+    assert_eq!(F7::random_primitive_root_of_unity(&mut rng, 2), None);
+}
+
+#[test]
+fn test_named_field() {
+    let mut rng = test_rng();
+
+    let to_fr = |a: FrNamed| Fr::from_repr(FrRepr(a.into_repr().0)).unwrap();
+
+    for _ in 0..1000 {
+        let a = FrNamed::rand(&mut rng);
+        let b = FrNamed::rand(&mut rng);
+        let (x, y) = (to_fr(a), to_fr(b));
+
+        let mut c = a;
+        c.mul_assign(&b);
+        c.add_assign(&a);
+        c.square();
+        c.sub_assign(&b);
+
+        let mut z = x;
+        z.mul_assign(&y);
+        z.add_assign(&x);
+        z.square();
+        z.sub_assign(&y);
+
+        assert_eq!(to_fr(c), z);
+        assert_eq!(a.inverse().map(to_fr), x.inverse());
+        assert_eq!(ff::SqrtField::sqrt(&a).map(to_fr), ff::SqrtField::sqrt(&x));
+    }
+
+    assert_eq!(FrNamed::from_repr(FrNamedRepr(Fr::char().0)), Err(ff::PrimeFieldDecodingError::NotInField));
+    assert_eq!(to_fr(FrNamed::multiplicative_generator()), Fr::multiplicative_generator());
+    assert_eq!(to_fr(FrNamed::root_of_unity()), Fr::root_of_unity());
+}
@@ -0,0 +1,395 @@
+extern crate rand;
+extern crate ff;
+
+// Each module derives a field over a safe prime whose size forces the given
+// number of limbs, and checks the generated multiplication and squaring.
+macro_rules! limb_tests {
+    ($module:ident, $field:ident, $repr:ident, $modulus:tt, $generator:tt) => {
+        limb_tests!($module, $field, $repr, $modulus, $generator, "64");
+    };
+    ($module:ident, $field:ident, $repr:ident, $modulus:tt, $generator:tt, $limb_size:tt) => {
+        mod $module {
+            use ff::{Field, PrimeField, PrimeFieldRepr};
+            use rand::{Rand, SeedableRng, XorShiftRng};
+
+            #[derive(PrimeField)]
+            #[PrimeFieldModulus = $modulus]
+            #[PrimeFieldGenerator = $generator]
+            #[PrimeFieldReprLimbSize = $limb_size]
+            pub struct $field($repr);
+
+            fn test_rng() -> XorShiftRng {
+                XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654])
+            }
+
+            #[test]
+            fn test_mul_commutativity() {
+                let mut rng = test_rng();
+
+                for _ in 0..1000 {
+                    let a = $field::rand(&mut rng);
+                    let b = $field::rand(&mut rng);
+
+                    let mut ab = a;
+                    ab.mul_assign(&b);
+                    let mut ba = b;
+                    ba.mul_assign(&a);
+
+                    assert_eq!(ab, ba);
+                }
+            }
+
+            #[test]
+            fn test_mul_distributivity() {
+                let mut rng = test_rng();
+
+                for _ in 0..1000 {
+                    let a = $field::rand(&mut rng);
+                    let b = $field::rand(&mut rng);
+                    let c = $field::rand(&mut rng);
+
+                    let mut lhs = b;
+                    lhs.add_assign(&c);
+                    lhs.mul_assign(&a);
+
+                    let mut ab = a;
+                    ab.mul_assign(&b);
+                    let mut ac = a;
+                    ac.mul_assign(&c);
+                    let mut rhs = ab;
+                    rhs.add_assign(&ac);
+
+                    assert_eq!(lhs, rhs);
+                }
+            }
+
+            #[test]
+            fn test_squaring() {
+                let mut rng = test_rng();
+
+                for _ in 0..1000 {
+                    let a = $field::rand(&mut rng);
+
+                    let mut sq = a;
+                    sq.square();
+                    let mut mul = a;
+                    mul.mul_assign(&a);
+
+                    assert_eq!(sq, mul);
+                }
+
+                // Check against a value with every limb saturated.
+                let mut a = $field::char();
+                a.sub_noborrow(&$repr::from(1));
+                let a = $field::from_repr(a).unwrap();
+
+                let mut sq = a;
+                sq.square();
+                assert_eq!(sq, $field::one());
+            }
+
+            #[cfg(feature = "subtle")]
+            #[test]
+            fn test_to_repr_ct() {
+                let mut rng = test_rng();
+
+                for _ in 0..100 {
+                    let a = $field::rand(&mut rng);
+                    assert_eq!(a.to_repr_ct(), a.into_repr());
+                }
+                assert_eq!($field::zero().to_repr_ct(), $repr::from(0));
+                assert_eq!($field::one().to_repr_ct(), $repr::from(1));
+            }
+
+            #[cfg(feature = "digest")]
+            #[test]
+            fn test_hash_into() {
+                // Collects the bytes fed to it.
+                struct Collect(Vec<u8>);
+
+                impl ff::digest::Update for Collect {
+                    fn update(&mut self, data: &[u8]) {
+                        self.0.extend_from_slice(data);
+                    }
+                }
+
+                let mut rng = test_rng();
+
+                for _ in 0..10 {
+                    let a = $field::rand(&mut rng);
+                    let mut collect = Collect(vec![]);
+                    a.hash_into(&mut collect);
+                    assert_eq!(collect.0, a.to_bytes());
+                }
+
+                let mut collect = Collect(vec![]);
+                $field::hash_char_into(&mut collect);
+                assert_eq!(collect.0, $field::modulus_bytes());
+            }
+
+            #[test]
+            fn test_validate_parameters() {
+                assert_eq!($field::validate_parameters(), Ok(()));
+            }
+
+            #[test]
+            fn test_mul_by_u64() {
+                let mut rng = test_rng();
+
+                let mut elements = vec![$field::zero(), $field::one(), $field::minus_one()];
+                elements.extend((0..100).map(|_| $field::rand(&mut rng)));
+
+                for x in elements {
+                    for &scalar in &[0, 1, 7, 0x8000000000000001, u64::MAX - 1, u64::MAX] {
+                        let mut a = x;
+                        a.mul_by_u64(scalar);
+
+                        let mut expected = x;
+                        expected.mul_assign(&$field::from_u64(scalar));
+                        assert_eq!(a, expected);
+                    }
+                }
+            }
+
+            #[test]
+            fn test_minus_one() {
+                let mut rng = test_rng();
+
+                let mut tmp = $field::minus_one();
+                tmp.add_assign(&$field::one());
+                assert!(tmp.is_zero());
+
+                let mut neg = $field::one();
+                neg.negate();
+                assert_eq!($field::minus_one(), neg);
+
+                for _ in 0..100 {
+                    let x = $field::rand(&mut rng);
+                    let mut tmp = x.one_minus();
+                    tmp.add_assign(&x);
+                    assert_eq!(tmp, $field::one());
+                }
+            }
+
+            #[test]
+            fn test_mul_by_self() {
+                // Safe code cannot pass `&x` to `x.mul_assign`, so multiplying an
+                // element by itself always goes through a copy, including through the
+                // operators. The generated multiplication reads every limb of both
+                // operands before the reduction writes the result.
+                let mut rng = test_rng();
+
+                for _ in 0..100 {
+                    let a = $field::rand(&mut rng);
+
+                    let mut sq = a;
+                    sq.square();
+
+                    let mut b = a;
+                    b *= b;
+                    assert_eq!(b, sq);
+
+                    let mut c = a;
+                    c = c * c;
+                    assert_eq!(c, sq);
+                }
+            }
+
+            #[test]
+            fn test_reduce_double_width() {
+                let mut rng = test_rng();
+
+                for _ in 0..1000 {
+                    let a = $field::rand(&mut rng);
+                    let b = $field::rand(&mut rng);
+
+                    // Multiply the canonical representations.
+                    let (x, y) = (a.into_repr(), b.into_repr());
+                    let limbs = x.as_ref().len();
+                    let mut prod = vec![0u64; limbs * 2];
+                    for i in 0..limbs {
+                        let mut carry = 0;
+                        for j in 0..limbs {
+                            prod[i + j] = ff::mac_with_carry(prod[i + j], x.as_ref()[i], y.as_ref()[j], &mut carry);
+                        }
+                        prod[i + limbs] = carry;
+                    }
+
+                    let mut lo = $repr::default();
+                    let mut hi = $repr::default();
+                    lo.as_mut().copy_from_slice(&prod[..limbs]);
+                    hi.as_mut().copy_from_slice(&prod[limbs..]);
+
+                    let mut ab = a;
+                    ab.mul_assign(&b);
+                    assert_eq!($field::reduce_double_width(lo, hi), ab);
+                }
+
+                // Halves beyond the modulus are reduced too: with every bit set,
+                // each half is 2^(64 * limbs) - 1.
+                let mut max = $repr::default();
+                for limb in max.as_mut() {
+                    *limb = !0;
+                }
+                let mut shift = $field::reduce_double_width($repr::from(0), $repr::from(1));
+                let mut max_half = $field::reduce_double_width(max, $repr::from(0));
+                max_half.add_assign(&$field::one());
+                assert_eq!(max_half, shift);
+
+                // (2^(64 * limbs) - 1) * 2^(64 * limbs) + (2^(64 * limbs) - 1)
+                shift.square();
+                shift.sub_assign(&$field::one());
+                assert_eq!($field::reduce_double_width(max, max), shift);
+            }
+
+            #[test]
+            fn test_sum_of_products() {
+                let mut rng = test_rng();
+
+                for n in 0..65 {
+                    let a: Vec<$field> = (0..n).map(|_| $field::rand(&mut rng)).collect();
+                    let b: Vec<$field> = (0..n).map(|_| $field::rand(&mut rng)).collect();
+
+                    let mut expected = $field::zero();
+                    for (a, b) in a.iter().zip(b.iter()) {
+                        let mut tmp = *a;
+                        tmp.mul_assign(b);
+                        expected.add_assign(&tmp);
+                    }
+
+                    assert_eq!($field::sum_of_products(&a, &b), expected);
+                }
+
+                // Saturate the accumulator with the largest products.
+                let mut max = $field::char();
+                max.sub_noborrow(&$repr::from(1));
+                let max = vec![$field::from_repr(max).unwrap(); 64];
+                assert_eq!($field::sum_of_products(&max, &max), $field::from_repr($repr::from(64)).unwrap());
+            }
+        }
+    }
+}
+
+limb_tests!(limbs1, F1, F1Repr,
+    "2962090390876548059",
+    "2");
+limb_tests!(limbs2, F2, F2Repr,
+    "43877429615775416728072844573419965167",
+    "5");
+// A modulus whose bit length is a multiple of 64, which still needs an extra limb.
+limb_tests!(limbs3_128, F3_128, F3_128Repr,
+    "340282366920938463463374607431768211297",
+    "5");
+limb_tests!(limbs3, F3, F3Repr,
+    "969908431206324775405559726270208506197898381752431841007",
+    "5");
+limb_tests!(limbs4, F4, F4Repr,
+    "20938951530444219524351370703484088351240057006041559427763410976366137328807",
+    "5");
+limb_tests!(limbs5, F5, F5Repr,
+    "302553293240830364843675570334058612445952238913919592955139245213959125153505044345066848194247",
+    "5");
+limb_tests!(limbs6, F6, F6Repr,
+    "6524839568633596090328539809767130383887515570236718327121473907554797054792235812333379953178937743552855371798759",
+    "7");
+limb_tests!(limbs7, F7, F7Repr,
+    "125547806529068132577093420193179083050346433919128097502484258423445450792522839435320234576880196892774897038305480014987317086598679",
+    "7");
+limb_tests!(limbs8, F8, F8Repr,
+    "2024729778959494908791997416089010323919819483715937644174349147639111817080304518322532160009489445904077005081295113363005432193595912326496365751733947",
+    "2");
+
+limb_tests!(limbs1_32, F1_32, F1_32Repr,
+    "2962090390876548059",
+    "2",
+    "32");
+limb_tests!(limbs2_32, F2_32, F2_32Repr,
+    "43877429615775416728072844573419965167",
+    "5",
+    "32");
+limb_tests!(limbs3_32, F3_32, F3_32Repr,
+    "969908431206324775405559726270208506197898381752431841007",
+    "5",
+    "32");
+limb_tests!(limbs4_32, F4_32, F4_32Repr,
+    "20938951530444219524351370703484088351240057006041559427763410976366137328807",
+    "5",
+    "32");
+limb_tests!(limbs5_32, F5_32, F5_32Repr,
+    "302553293240830364843675570334058612445952238913919592955139245213959125153505044345066848194247",
+    "5",
+    "32");
+limb_tests!(limbs6_32, F6_32, F6_32Repr,
+    "6524839568633596090328539809767130383887515570236718327121473907554797054792235812333379953178937743552855371798759",
+    "7",
+    "32");
+limb_tests!(limbs7_32, F7_32, F7_32Repr,
+    "125547806529068132577093420193179083050346433919128097502484258423445450792522839435320234576880196892774897038305480014987317086598679",
+    "7",
+    "32");
+limb_tests!(limbs8_32, F8_32, F8_32Repr,
+    "2024729778959494908791997416089010323919819483715937644174349147639111817080304518322532160009489445904077005081295113363005432193595912326496365751733947",
+    "2",
+    "32");
+
+#[test]
+fn test_low_u128_single_limb() {
+    use ff::PrimeFieldRepr;
+
+    assert_eq!(limbs1::F1Repr::from(5 << 60).low_u128(), 5u128 << 60);
+    assert_eq!(limbs1::F1Repr::from_u128(3 << 64 | 9).low_u128(), 9);
+}
+
+#[test]
+fn test_from_u64_single_limb() {
+    use ff::PrimeField;
+
+    // The modulus fits in a u64, so larger values are reduced.
+    let p = limbs1::F1::char().as_ref()[0];
+    assert_eq!(limbs1::F1::from_u64(p), limbs1::F1::from_u64(0));
+    assert_eq!(limbs1::F1::from_u64(p + 5), limbs1::F1::from_u64(5));
+    assert_eq!(limbs1::F1::from_u64(!0), limbs1::F1::from_u64(!0 - p));
+    assert_eq!(limbs1_32::F1_32::from_u64(!0), limbs1_32::F1_32::from_u64(!0 - p));
+}
+
+#[test]
+fn test_small_field_matches_montgomery() {
+    use ff::{Field, PrimeField, SqrtField};
+    use limbs1::{F1, F1Repr};
+    use limbs1_32::{F1_32, F1_32Repr};
+    use rand::{Rand, SeedableRng, XorShiftRng};
+
+    // With the small-field feature, F1 is not kept in Montgomery form, while F1_32
+    // always is. Their canonical values must agree.
+    assert!(F1::characteristic_fits_in_u64());
+    assert!(!limbs2::F2::characteristic_fits_in_u64());
+
+    let to_32 = |a: F1| F1_32::from_repr(F1_32Repr(a.into_repr().0)).unwrap();
+    let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    for _ in 0..1000 {
+        let a = F1::rand(&mut rng);
+        let b = F1::rand(&mut rng);
+        let (a32, b32) = (to_32(a), to_32(b));
+
+        assert_eq!(to_32(a * b), a32 * b32);
+        assert_eq!(to_32(a + b), a32 + b32);
+        assert_eq!(to_32(a - b), a32 - b32);
+
+        let mut sq = a;
+        sq.square();
+        let mut sq32 = a32;
+        sq32.square();
+        assert_eq!(to_32(sq), sq32);
+
+        assert_eq!(a.inverse().map(to_32), a32.inverse());
+        assert_eq!(a.sqrt().map(to_32), a32.sqrt());
+        assert_eq!(to_32(a.pow([0x123456789abcdef])), a32.pow([0x123456789abcdef]));
+        assert_eq!(to_32(F1::sum_of_products(&[a, b], &[b, a])), F1_32::sum_of_products(&[a32, b32], &[b32, a32]));
+    }
+
+    assert_eq!(F1::from_repr(F1Repr::from(5)).unwrap().into_repr(), F1Repr::from(5));
+    assert_eq!(to_32(F1::from_u64(!0)), F1_32::from_u64(!0));
+    assert_eq!(to_32(F1::multiplicative_generator()), F1_32::multiplicative_generator());
+    assert_eq!(to_32(F1::root_of_unity()), F1_32::root_of_unity());
+}